@@ -0,0 +1,184 @@
+use std::time::{Duration, Instant};
+
+use nalgebra::Vector2;
+
+use crate::{
+    board::{Board, Orientation, Square},
+    piece::{Piece, Type},
+};
+
+/// a piece sliding from one square to another
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tween {
+    pub piece: Piece,
+    pub from: Square,
+    pub to: Square,
+}
+
+/// a piece that disappeared without moving anywhere, i.e. it was captured
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fade {
+    pub piece: Piece,
+    pub square: Square,
+}
+
+/// the transition between two boards, found by diffing them
+#[derive(Debug, Clone)]
+pub struct Animation {
+    start: Instant,
+    duration: Duration,
+    pub tweens: Vec<Tween>,
+    pub fades: Vec<Fade>,
+}
+
+fn squares() -> impl Iterator<Item = Square> {
+    (0..8).flat_map(|rank| (0..8).map(move |file| Square::new(file, rank)))
+}
+
+fn distance(a: Square, b: Square) -> usize {
+    let files = (a.file as isize - b.file as isize).abs();
+    let ranks = (a.rank as isize - b.rank as isize).abs();
+    (files * files + ranks * ranks) as usize
+}
+
+/// index of the vacated square closest to `to` whose piece satisfies `predicate`
+fn closest(
+    vacated: &[(Square, Piece)],
+    to: Square,
+    predicate: impl Fn(Piece) -> bool,
+) -> Option<usize> {
+    vacated
+        .iter()
+        .enumerate()
+        .filter(|(_, &(_, piece))| predicate(piece))
+        .min_by_key(|(_, &(from, _))| distance(from, to))
+        .map(|(i, _)| i)
+}
+
+impl Animation {
+    /// returns `None` when nothing changed between the boards
+    pub fn between(old: &Board, new: &Board, duration: Duration) -> Option<Animation> {
+        let mut vacated: Vec<_> = squares()
+            .filter_map(|square| match square.piece(old) {
+                Some(piece) if square.piece(new) != Some(piece) => Some((square, piece)),
+                _ => None,
+            })
+            .collect();
+        let arrived: Vec<_> = squares()
+            .filter_map(|square| match square.piece(new) {
+                Some(piece) if square.piece(old) != Some(piece) => Some((square, piece)),
+                _ => None,
+            })
+            .collect();
+        if vacated.is_empty() && arrived.is_empty() {
+            return None;
+        }
+
+        // identical pieces are matched up first so a promoting pawn can't steal the source
+        // square of an ordinary move
+        let mut tweens = Vec::new();
+        let mut unmatched = Vec::new();
+        for &(to, piece) in &arrived {
+            match closest(&vacated, to, |p| p == piece) {
+                Some(i) => {
+                    let (from, _) = vacated.remove(i);
+                    tweens.push(Tween { piece, from, to });
+                }
+                None => unmatched.push((to, piece)),
+            }
+        }
+        for (to, piece) in unmatched {
+            let is_promotion = |p: Piece| p.color == piece.color && p.type_ == Type::Pawn;
+            if let Some(i) = closest(&vacated, to, is_promotion) {
+                let (from, _) = vacated.remove(i);
+                tweens.push(Tween { piece, from, to });
+            }
+        }
+
+        let fades = vacated
+            .into_iter()
+            .map(|(square, piece)| Fade { piece, square })
+            .collect();
+
+        Some(Animation {
+            start: Instant::now(),
+            duration,
+            tweens,
+            fades,
+        })
+    }
+
+    pub fn is_finished(&self, now: Instant) -> bool {
+        now.duration_since(self.start) >= self.duration
+    }
+
+    /// eased progress through the animation from 0 to 1
+    pub fn progress(&self, now: Instant) -> f32 {
+        if self.duration == Duration::from_secs(0) {
+            return 1.0;
+        }
+        let t = now.duration_since(self.start).as_secs_f32() / self.duration.as_secs_f32();
+        ease_in_out_cubic(t.min(1.0))
+    }
+
+    /// drops the tween arriving on `to`, so that piece is drawn on its square right away
+    pub fn skip_tween(&mut self, to: Square) {
+        self.tweens.retain(|tween| tween.to != to);
+    }
+
+    pub fn is_tween_target(&self, square: Square) -> bool {
+        self.tweens.iter().any(|tween| tween.to == square)
+    }
+}
+
+impl Tween {
+    /// position in squares at the given progress
+    pub fn position(&self, progress: f32, orientation: Orientation) -> Vector2<f32> {
+        let from = self.from.oriented(orientation);
+        let to = self.to.oriented(orientation);
+        let from = Vector2::new(from.file as f32, from.rank as f32);
+        let to = Vector2::new(to.file as f32, to.rank as f32);
+        from.lerp(&to, progress)
+    }
+}
+
+impl Fade {
+    pub fn position(&self, orientation: Orientation) -> Vector2<f32> {
+        let square = self.square.oriented(orientation);
+        Vector2::new(square.file as f32, square.rank as f32)
+    }
+}
+
+pub fn ease_in_out_cubic(t: f32) -> f32 {
+    if t < 0.5 {
+        4.0 * t * t * t
+    } else {
+        1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::{Move, Position};
+
+    fn square(name: &str) -> Square {
+        Square::from_name(name).unwrap()
+    }
+
+    #[test]
+    fn skipping_the_king_still_moves_the_rook() {
+        let old = Position::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        let mut new = old;
+        assert!(new.play(Move::new(square("e1"), square("g1"))));
+        let mut animation =
+            Animation::between(&old.board, &new.board, Duration::from_secs(1)).unwrap();
+        assert_eq!(animation.tweens.len(), 2);
+
+        animation.skip_tween(square("g1"));
+        assert!(!animation.is_tween_target(square("g1")));
+        assert_eq!(animation.tweens.len(), 1);
+        assert_eq!(animation.tweens[0].from, square("h1"));
+        assert_eq!(animation.tweens[0].to, square("f1"));
+    }
+}
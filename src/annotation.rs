@@ -0,0 +1,364 @@
+use bytemuck::{Pod, Zeroable};
+use nalgebra::{Matrix4, Vector2, Vector3, Vector4};
+
+use crate::board::{Orientation, Square};
+
+/// colors available for annotations, named after the letters used by PGN `%cal`/`%csl`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Brush {
+    Green,
+    Red,
+    Yellow,
+    Blue,
+}
+
+impl Brush {
+    pub fn from_letter(letter: char) -> Option<Brush> {
+        match letter.to_ascii_uppercase() {
+            'G' => Some(Brush::Green),
+            'R' => Some(Brush::Red),
+            'Y' => Some(Brush::Yellow),
+            'B' => Some(Brush::Blue),
+            _ => None,
+        }
+    }
+
+    pub fn letter(&self) -> char {
+        match self {
+            Brush::Green => 'G',
+            Brush::Red => 'R',
+            Brush::Yellow => 'Y',
+            Brush::Blue => 'B',
+        }
+    }
+
+    /// linear rgba of the brush
+    pub fn rgba(&self) -> [f32; 4] {
+        match self {
+            Brush::Green => [0.01, 0.19, 0.01, 0.8],
+            Brush::Red => [0.25, 0.01, 0.01, 0.8],
+            Brush::Yellow => [0.79, 0.27, 0.0, 0.8],
+            Brush::Blue => [0.0, 0.03, 0.25, 0.8],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Annotation {
+    Arrow {
+        from: Square,
+        to: Square,
+        brush: Brush,
+    },
+    Circle {
+        square: Square,
+        brush: Brush,
+    },
+}
+
+/// parses the `[%cal ...]` and `[%csl ...]` commands of a PGN comment, anything else in the
+/// comment is ignored as are malformed entries
+pub fn parse_comment(comment: &str) -> Vec<Annotation> {
+    let mut annotations = Vec::new();
+    let mut rest = comment;
+    while let Some(start) = rest.find("[%") {
+        let command = &rest[start + 2..];
+        let end = match command.find(']') {
+            Some(end) => end,
+            None => break,
+        };
+        let mut words = command[..end].split_whitespace();
+        let kind = words.next();
+        for entry in words.flat_map(|w| w.split(',')).filter(|e| !e.is_empty()) {
+            let annotation = match kind {
+                Some("cal") => parse_arrow(entry),
+                Some("csl") => parse_circle(entry),
+                _ => None,
+            };
+            annotations.extend(annotation);
+        }
+        rest = &command[end + 1..];
+    }
+    annotations
+}
+
+fn parse_arrow(entry: &str) -> Option<Annotation> {
+    let brush = Brush::from_letter(entry.chars().next()?)?;
+    Some(Annotation::Arrow {
+        from: Square::from_name(entry.get(1..3)?)?,
+        to: Square::from_name(entry.get(3..)?)?,
+        brush,
+    })
+}
+
+fn parse_circle(entry: &str) -> Option<Annotation> {
+    let brush = Brush::from_letter(entry.chars().next()?)?;
+    Some(Annotation::Circle {
+        square: Square::from_name(entry.get(1..)?)?,
+        brush,
+    })
+}
+
+// all sizes are in squares
+const RING_RADIUS: f32 = 0.47;
+const RING_THICKNESS: f32 = 0.07;
+const SHAFT_WIDTH: f32 = 0.18;
+const HEAD_LENGTH: f32 = 0.42;
+const HEAD_WIDTH: f32 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Shape {
+    Ring,
+    Shaft,
+    Head,
+}
+
+/// one of the shapes an annotation is made of, in squares from the bottom left corner of
+/// the board as it's shown
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Primitive {
+    pub shape: Shape,
+    pub center: Vector2<f32>,
+    /// the direction of the shape's x axis
+    pub dir: Vector2<f32>,
+    pub half_size: Vector2<f32>,
+    /// how wide a ring's stroke is
+    pub thickness: f32,
+}
+
+fn square_center(square: Square) -> Vector2<f32> {
+    Vector2::new(square.file as f32 + 0.5, square.rank as f32 + 0.5)
+}
+
+impl Annotation {
+    pub fn brush(&self) -> Brush {
+        match *self {
+            Annotation::Arrow { brush, .. } | Annotation::Circle { brush, .. } => brush,
+        }
+    }
+
+    /// breaks the annotation down into the primitive shapes drawn by the annotation shader
+    pub fn to_raw(&self, orientation: Orientation) -> Vec<AnnotationRaw> {
+        let color = self.brush().rgba().into();
+        self.primitives(orientation)
+            .iter()
+            .map(|primitive| AnnotationRaw::new(primitive, color))
+            .collect()
+    }
+
+    pub(crate) fn primitives(&self, orientation: Orientation) -> Vec<Primitive> {
+        match self.oriented(orientation) {
+            Annotation::Circle { square, .. } => vec![ring(square)],
+            Annotation::Arrow { from, to, .. } if from == to => vec![ring(from)],
+            Annotation::Arrow { from, to, .. } => {
+                let file_jump = (from.file as isize - to.file as isize).abs();
+                let rank_jump = (from.rank as isize - to.rank as isize).abs();
+                let is_knight_jump = file_jump * rank_jump == 2;
+
+                let from = square_center(from);
+                let to = square_center(to);
+                let mut primitives = Vec::with_capacity(3);
+                let mut start = from;
+                if is_knight_jump {
+                    // the long leg goes first, extended by half a shaft so the corner is filled
+                    let corner = if file_jump > rank_jump {
+                        Vector2::new(to.x, from.y)
+                    } else {
+                        Vector2::new(from.x, to.y)
+                    };
+                    let dir = (corner - from).normalize();
+                    primitives.push(shaft(from, corner + dir * SHAFT_WIDTH / 2.0));
+                    start = corner + (to - corner).normalize() * SHAFT_WIDTH / 2.0;
+                }
+                let dir = (to - start).normalize();
+                let head_base = to - dir * HEAD_LENGTH;
+                primitives.push(shaft(start, head_base));
+                primitives.push(Primitive {
+                    shape: Shape::Head,
+                    center: head_base + dir * HEAD_LENGTH / 2.0,
+                    dir,
+                    half_size: Vector2::new(HEAD_LENGTH / 2.0, HEAD_WIDTH / 2.0),
+                    thickness: 0.0,
+                });
+                primitives
+            }
+        }
+    }
+}
+
+impl Annotation {
+    fn oriented(&self, orientation: Orientation) -> Annotation {
+        match *self {
+            Annotation::Arrow { from, to, brush } => Annotation::Arrow {
+                from: from.oriented(orientation),
+                to: to.oriented(orientation),
+                brush,
+            },
+            Annotation::Circle { square, brush } => Annotation::Circle {
+                square: square.oriented(orientation),
+                brush,
+            },
+        }
+    }
+}
+
+fn ring(square: Square) -> Primitive {
+    Primitive {
+        shape: Shape::Ring,
+        center: square_center(square),
+        dir: Vector2::x(),
+        half_size: Vector2::new(RING_RADIUS, RING_RADIUS),
+        thickness: RING_THICKNESS,
+    }
+}
+
+fn shaft(start: Vector2<f32>, end: Vector2<f32>) -> Primitive {
+    let delta = end - start;
+    Primitive {
+        shape: Shape::Shaft,
+        center: start + delta / 2.0,
+        dir: delta.normalize(),
+        half_size: Vector2::new(delta.norm() / 2.0, SHAFT_WIDTH / 2.0),
+        thickness: 0.0,
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct AnnotationRaw {
+    position: Matrix4<f32>,
+    color: Vector4<f32>,
+    size: Vector3<f32>,
+    shape: u32,
+}
+
+impl AnnotationRaw {
+    fn new(primitive: &Primitive, color: Vector4<f32>) -> AnnotationRaw {
+        let Primitive {
+            shape,
+            center,
+            dir,
+            half_size,
+            thickness,
+        } = *primitive;
+        let angle = dir.y.atan2(dir.x);
+        let world_center = center / 4.0 - Vector2::new(1.0, 1.0);
+        let position = Matrix4::new_translation(&Vector3::new(world_center.x, world_center.y, 0.0))
+            * Matrix4::new_rotation(Vector3::new(0.0, 0.0, angle))
+            * Matrix4::new_scaling(1.0 / 4.0);
+        AnnotationRaw {
+            position,
+            color,
+            size: Vector3::new(half_size.x, half_size.y, thickness),
+            shape: shape as u32,
+        }
+    }
+
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        use std::mem;
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<AnnotationRaw>() as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Float4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 12]>() as wgpu::BufferAddress,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Float4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 16]>() as wgpu::BufferAddress,
+                    shader_location: 6,
+                    format: wgpu::VertexFormat::Float4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 20]>() as wgpu::BufferAddress,
+                    shader_location: 7,
+                    format: wgpu::VertexFormat::Float3,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 23]>() as wgpu::BufferAddress,
+                    shader_location: 8,
+                    format: wgpu::VertexFormat::Uint,
+                },
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(name: &str) -> Square {
+        Square::from_name(name).unwrap()
+    }
+
+    fn arrow(from: &str, to: &str, brush: Brush) -> Annotation {
+        Annotation::Arrow {
+            from: square(from),
+            to: square(to),
+            brush,
+        }
+    }
+
+    fn circle(name: &str, brush: Brush) -> Annotation {
+        Annotation::Circle {
+            square: square(name),
+            brush,
+        }
+    }
+
+    #[test]
+    fn arrows_and_circles() {
+        assert_eq!(
+            parse_comment("[%cal Ge2e4,Rd1h5] [%csl Yd4,bf7]"),
+            vec![
+                arrow("e2", "e4", Brush::Green),
+                arrow("d1", "h5", Brush::Red),
+                circle("d4", Brush::Yellow),
+                circle("f7", Brush::Blue),
+            ]
+        );
+    }
+
+    #[test]
+    fn commands_among_text() {
+        assert_eq!(
+            parse_comment("a strong move [%clk 0:01:00] [%csl Ge5] keeps [%cal Bg1f3] the edge"),
+            vec![circle("e5", Brush::Green), arrow("g1", "f3", Brush::Blue)]
+        );
+        assert_eq!(parse_comment("no commands [here]"), Vec::new());
+    }
+
+    #[test]
+    fn malformed_entries_are_skipped() {
+        assert_eq!(
+            parse_comment("[%cal Xe2e4,Ge2,Ge2e9,Gi1a1,,Ge2e4] [%csl G,Zd4,Gd44,Rh8]"),
+            vec![arrow("e2", "e4", Brush::Green), circle("h8", Brush::Red)]
+        );
+        // an unclosed command ends the comment
+        assert_eq!(
+            parse_comment("[%csl Ge5] [%cal Ge2e4"),
+            vec![circle("e5", Brush::Green)]
+        );
+        assert_eq!(
+            parse_comment("[%csl Gé5,Ge5]"),
+            vec![circle("e5", Brush::Green)]
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use std::ops::Range;
+
+use wgpu::util::DeviceExt;
+
+use crate::{
+    annotation::{Annotation, AnnotationRaw},
+    board::{Board, Orientation},
+    quad::{INDICES, LAYOUT, VERTICES},
+    renderable::Renderable,
+};
+
+const MAX_SHAPES: usize = 256;
+
+type ShapesBuffer = [AnnotationRaw; MAX_SHAPES];
+
+/// draws arrows and circles on top of the pieces
+pub struct AnnotationsView {
+    pipeline: wgpu::RenderPipeline,
+    vert_buffer: wgpu::Buffer,
+    idx_buffer: wgpu::Buffer,
+    shape_buffer: wgpu::Buffer,
+    indices: Range<u32>,
+    annotations: Vec<Annotation>,
+    /// the contents of `shape_buffer`, so it's only written when something changed
+    uploaded: Vec<AnnotationRaw>,
+}
+
+impl AnnotationsView {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> AnnotationsView {
+        let quad_vert =
+            device.create_shader_module(&wgpu::include_spirv!("shaders/annotation.vert.spv"));
+        let quad_frag =
+            device.create_shader_module(&wgpu::include_spirv!("shaders/annotation.frag.spv"));
+
+        let vert_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Annotation Vertex Buffer"),
+            contents: bytemuck::cast_slice(VERTICES),
+            usage: wgpu::BufferUsage::VERTEX,
+        });
+
+        let idx_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Annotation Index Buffer"),
+            contents: bytemuck::cast_slice(INDICES),
+            usage: wgpu::BufferUsage::INDEX,
+        });
+
+        let indices = 0..INDICES.len() as u32;
+
+        let shape_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Annotation Shape Buffer"),
+            size: std::mem::size_of::<ShapesBuffer>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Annotations Render Pipeline Layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Annotations Render Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &quad_vert,
+                entry_point: "main",
+                buffers: &[LAYOUT, AnnotationRaw::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &quad_frag,
+                entry_point: "main",
+                targets: &[wgpu::ColorTargetState {
+                    format,
+                    alpha_blend: wgpu::BlendState {
+                        src_factor: wgpu::BlendFactor::SrcAlpha,
+                        dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                    color_blend: wgpu::BlendState {
+                        src_factor: wgpu::BlendFactor::SrcAlpha,
+                        dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                    write_mask: wgpu::ColorWrite::ALL,
+                }],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: wgpu::CullMode::Back,
+                polygon_mode: wgpu::PolygonMode::Fill,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+        });
+
+        AnnotationsView {
+            pipeline,
+            vert_buffer,
+            idx_buffer,
+            shape_buffer,
+            indices,
+            annotations: Vec::new(),
+            uploaded: Vec::new(),
+        }
+    }
+
+    pub fn annotations(&self) -> &[Annotation] {
+        &self.annotations
+    }
+
+    /// adds an annotation, returns false if an identical one is already shown
+    pub fn add(&mut self, annotation: Annotation) -> bool {
+        if self.annotations.contains(&annotation) {
+            return false;
+        }
+        self.annotations.push(annotation);
+        true
+    }
+
+    /// removes an annotation, returns false if it wasn't shown
+    pub fn remove(&mut self, annotation: &Annotation) -> bool {
+        let len = self.annotations.len();
+        self.annotations.retain(|a| a != annotation);
+        self.annotations.len() != len
+    }
+
+    pub fn clear(&mut self) {
+        self.annotations.clear();
+    }
+}
+
+/// the shapes `annotations` are drawn with, as many of them as fit in the buffer
+fn shapes(annotations: &[Annotation], orientation: Orientation) -> Vec<AnnotationRaw> {
+    let mut shapes: Vec<_> = annotations
+        .iter()
+        .flat_map(|a| a.to_raw(orientation))
+        .collect();
+    if shapes.len() > MAX_SHAPES {
+        log::warn!("too many annotations, only drawing {} shapes", MAX_SHAPES);
+        shapes.truncate(MAX_SHAPES);
+    }
+    shapes
+}
+
+impl Renderable for AnnotationsView {
+    fn prepare(&mut self, queue: &wgpu::Queue, _board: &Board, orientation: Orientation) {
+        let shapes = shapes(&self.annotations, orientation);
+        if shapes != self.uploaded {
+            if !shapes.is_empty() {
+                queue.write_buffer(&self.shape_buffer, 0, bytemuck::cast_slice(&shapes));
+            }
+            self.uploaded = shapes;
+        }
+    }
+
+    fn render<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if self.uploaded.is_empty() {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_vertex_buffer(0, self.vert_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, self.shape_buffer.slice(..));
+        render_pass.set_index_buffer(self.idx_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        render_pass.draw_indexed(self.indices.clone(), 0, 0..self.uploaded.len() as u32);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{annotation::Brush, board::Square};
+
+    #[test]
+    fn shapes_are_truncated() {
+        // each knight jump is two shafts and a head
+        let jump = |file: usize| Annotation::Arrow {
+            from: Square::new(file % 6, 0),
+            to: Square::new(file % 6 + 2, 1),
+            brush: Brush::Green,
+        };
+        let annotations: Vec<_> = (0..100).map(jump).collect();
+        assert_eq!(jump(0).to_raw(Orientation::White).len(), 3);
+        assert_eq!(shapes(&annotations, Orientation::White).len(), MAX_SHAPES);
+        assert_eq!(shapes(&annotations[..10], Orientation::White).len(), 30);
+    }
+}
@@ -0,0 +1,126 @@
+use bytemuck::{Pod, Zeroable};
+use image::{imageops, Rgba, RgbaImage};
+
+use crate::piece_set::PieceSet;
+
+/// the smallest gap left around each piece, in pixels of the full size atlas
+const MIN_PADDING: u32 = 2;
+
+/// std140 layout of the `Atlas` uniform block in `piece.frag`, where each piece sits inside
+/// its padded cell
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Pod, Zeroable)]
+pub struct AtlasUniform {
+    /// corner of the piece in its cell, as a fraction of the cell
+    pub offset: [f32; 2],
+    /// size of the piece as a fraction of the cell
+    pub scale: f32,
+    _padding: f32,
+}
+
+/// a piece set laid out for mipmapping: every piece is surrounded by transparent padding
+/// and its cell is a power of two, so cells never bleed into each other when downsampled
+pub struct MipmappedAtlas {
+    /// the atlas flipped vertically for upload, followed by each halved level down to a
+    /// single pixel per cell
+    pub levels: Vec<RgbaImage>,
+    pub uniform: AtlasUniform,
+}
+
+impl MipmappedAtlas {
+    pub fn new(set: &PieceSet) -> MipmappedAtlas {
+        let cell_size = set.cell_size().max(1);
+        let padded_size = (cell_size + 2 * MIN_PADDING).next_power_of_two();
+        let padding = (padded_size - cell_size) / 2;
+        let columns = set.atlas().width() / cell_size;
+        let rows = set.atlas().height() / cell_size;
+
+        let mut atlas = RgbaImage::new(columns * padded_size, rows * padded_size);
+        for row in 0..rows {
+            for column in 0..columns {
+                let cell = imageops::crop_imm(
+                    set.atlas(),
+                    column * cell_size,
+                    row * cell_size,
+                    cell_size,
+                    cell_size,
+                );
+                imageops::replace(
+                    &mut atlas,
+                    &cell,
+                    column * padded_size + padding,
+                    row * padded_size + padding,
+                );
+            }
+        }
+        let atlas = imageops::flip_vertical(&atlas);
+
+        let mut levels = vec![atlas];
+        let mut level_size = padded_size;
+        while level_size > 1 {
+            let next = downsample(levels.last().unwrap());
+            levels.push(next);
+            level_size /= 2;
+        }
+
+        // flipping moves the odd pixel of uneven padding to the other side vertically
+        let flipped_padding = padded_size - cell_size - padding;
+        let uniform = AtlasUniform {
+            offset: [
+                padding as f32 / padded_size as f32,
+                flipped_padding as f32 / padded_size as f32,
+            ],
+            scale: cell_size as f32 / padded_size as f32,
+            _padding: 0.0,
+        };
+        MipmappedAtlas { levels, uniform }
+    }
+}
+
+/// halves `image` with a box filter, averaging in linear space with premultiplied alpha so
+/// the transparent padding doesn't darken the edges of the pieces
+fn downsample(image: &RgbaImage) -> RgbaImage {
+    let to_linear: Vec<f32> = (0..=255u8).map(srgb_to_linear).collect();
+    let (width, height) = ((image.width() / 2).max(1), (image.height() / 2).max(1));
+    RgbaImage::from_fn(width, height, |x, y| {
+        let mut sum = [0.0f32; 4];
+        for (dx, dy) in &[(0, 0), (1, 0), (0, 1), (1, 1)] {
+            let x = (x * 2 + dx).min(image.width() - 1);
+            let y = (y * 2 + dy).min(image.height() - 1);
+            let Rgba([r, g, b, a]) = *image.get_pixel(x, y);
+            let alpha = a as f32 / 255.0;
+            sum[0] += to_linear[r as usize] * alpha;
+            sum[1] += to_linear[g as usize] * alpha;
+            sum[2] += to_linear[b as usize] * alpha;
+            sum[3] += alpha;
+        }
+        if sum[3] == 0.0 {
+            return Rgba([0, 0, 0, 0]);
+        }
+        Rgba([
+            linear_to_srgb(sum[0] / sum[3]),
+            linear_to_srgb(sum[1] / sum[3]),
+            linear_to_srgb(sum[2] / sum[3]),
+            (sum[3] / 4.0 * 255.0).round() as u8,
+        ])
+    })
+}
+
+pub fn srgb_to_linear(value: u8) -> f32 {
+    let value = value as f32 / 255.0;
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn linear_to_srgb(value: f32) -> u8 {
+    let value = value.clamp(0.0, 1.0);
+    let value = if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    };
+    (value * 255.0).round() as u8
+}
@@ -1,20 +1,73 @@
+use bytemuck::{Pod, Zeroable};
 use wgpu::util::DeviceExt;
 
 use crate::{
-    board::Board,
+    board::{Board, Orientation},
     quad::{INDICES, LAYOUT, VERTICES},
     renderable::Renderable,
+    theme::{Highlights, Theme},
 };
 
+/// std140 layout of the `Theme` uniform block in `board.frag`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+struct ThemeUniform {
+    light: [f32; 4],
+    dark: [f32; 4],
+    last_move: [f32; 4],
+    selected: [f32; 4],
+    check: [f32; 4],
+    last_move_squares: [u32; 2],
+    selected_squares: [u32; 2],
+    check_squares: [u32; 2],
+    _padding: [u32; 2],
+}
+
+fn split_mask(mask: u64) -> [u32; 2] {
+    [mask as u32, (mask >> 32) as u32]
+}
+
+impl ThemeUniform {
+    fn new(theme: &Theme, highlights: &Highlights, orientation: Orientation) -> ThemeUniform {
+        // the shader indexes squares as they appear on screen, which for a flipped board is
+        // the same as reversing the order of the squares
+        let orient = |mask: u64| match orientation {
+            Orientation::White => split_mask(mask),
+            Orientation::Black => split_mask(mask.reverse_bits()),
+        };
+        ThemeUniform {
+            light: theme.light,
+            dark: theme.dark,
+            last_move: theme.last_move,
+            selected: theme.selected,
+            check: theme.check,
+            last_move_squares: orient(highlights.last_move),
+            selected_squares: orient(highlights.selected),
+            check_squares: orient(highlights.check),
+            _padding: [0; 2],
+        }
+    }
+}
+
 pub struct Background {
     pipeline: wgpu::RenderPipeline,
     vert_buffer: wgpu::Buffer,
     idx_buffer: wgpu::Buffer,
     idx_num: u32,
+    theme_buffer: wgpu::Buffer,
+    theme_bind_group: wgpu::BindGroup,
+    theme: Theme,
+    highlights: Highlights,
+    /// the contents of `theme_buffer`, so it's only written when something changed
+    uploaded: ThemeUniform,
 }
 
 impl Background {
-    pub fn new(device: &wgpu::Device, sc_desc: &wgpu::SwapChainDescriptor) -> Background {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Background {
         let quad_vert =
             device.create_shader_module(&wgpu::include_spirv!("shaders/board.vert.spv"));
         let quad_frag =
@@ -34,9 +87,43 @@ impl Background {
 
         let idx_num = INDICES.len() as u32;
 
+        let theme = Theme::default();
+        let highlights = Highlights::default();
+
+        let uploaded = ThemeUniform::new(&theme, &highlights, Orientation::White);
+        let theme_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Theme Uniform Buffer"),
+            contents: bytemuck::bytes_of(&uploaded),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+
+        let theme_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: Some("Theme Bind Group Layout"),
+            });
+
+        let theme_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &theme_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: theme_buffer.as_entire_binding(),
+            }],
+            label: Some("Theme Bind Group"),
+        });
+
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("Quad Render Pipeline Layout"),
-            bind_group_layouts: &[],
+            bind_group_layouts: &[&theme_bind_group_layout],
             push_constant_ranges: &[],
         });
 
@@ -52,7 +139,7 @@ impl Background {
                 module: &quad_frag,
                 entry_point: "main",
                 targets: &[wgpu::ColorTargetState {
-                    format: sc_desc.format,
+                    format,
                     alpha_blend: wgpu::BlendState::REPLACE,
                     color_blend: wgpu::BlendState::REPLACE,
                     write_mask: wgpu::ColorWrite::ALL,
@@ -67,7 +154,7 @@ impl Background {
             },
             depth_stencil: None,
             multisample: wgpu::MultisampleState {
-                count: 1,
+                count: sample_count,
                 mask: !0,
                 alpha_to_coverage_enabled: false,
             },
@@ -78,31 +165,40 @@ impl Background {
             vert_buffer,
             idx_buffer,
             idx_num,
+            theme_buffer,
+            theme_bind_group,
+            theme,
+            highlights,
+            uploaded,
         }
     }
+
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    /// the new colors are uploaded on the next render, the pipeline stays as it is
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    pub fn highlights_mut(&mut self) -> &mut Highlights {
+        &mut self.highlights
+    }
 }
 
 impl Renderable for Background {
-    fn render(
-        &self,
-        encoder: &mut wgpu::CommandEncoder,
-        _queue: &mut wgpu::Queue,
-        frame: &wgpu::SwapChainTexture,
-        _board: &Board,
-    ) {
-        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("Render Pass"),
-            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                attachment: &frame.view,
-                resolve_target: None,
-                ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Load,
-                    store: true,
-                },
-            }],
-            depth_stencil_attachment: None,
-        });
+    fn prepare(&mut self, queue: &wgpu::Queue, _board: &Board, orientation: Orientation) {
+        let uniform = ThemeUniform::new(&self.theme, &self.highlights, orientation);
+        if uniform != self.uploaded {
+            queue.write_buffer(&self.theme_buffer, 0, bytemuck::bytes_of(&uniform));
+            self.uploaded = uniform;
+        }
+    }
+
+    fn render<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
         render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.theme_bind_group, &[]);
         render_pass.set_vertex_buffer(0, self.vert_buffer.slice(..));
         render_pass.set_index_buffer(self.idx_buffer.slice(..), wgpu::IndexFormat::Uint16);
         render_pass.draw_indexed(0..self.idx_num, 0, 0..1);
@@ -0,0 +1,205 @@
+use std::ops::Range;
+
+use wgpu::util::DeviceExt;
+
+use crate::quad::{INDICES, LAYOUT, VERTICES};
+
+/// copies a texture into part of another with a textured quad, used to draw a multisampled
+/// board into a target we can't resolve into directly without overwriting the rest of it
+pub struct Blit {
+    pipeline: wgpu::RenderPipeline,
+    vert_buffer: wgpu::Buffer,
+    idx_buffer: wgpu::Buffer,
+    indices: Range<u32>,
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+}
+
+/// the textures the board is drawn and resolved into before being blitted
+pub struct Offscreen {
+    pub size: (u32, u32),
+    pub multisampled: wgpu::TextureView,
+    pub resolved: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+}
+
+impl Blit {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Blit {
+        let quad_vert =
+            device.create_shader_module(&wgpu::include_spirv!("shaders/board.vert.spv"));
+        let quad_frag = device.create_shader_module(&wgpu::include_spirv!("shaders/blit.frag.spv"));
+
+        let vert_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Blit Vertex Buffer"),
+            contents: bytemuck::cast_slice(VERTICES),
+            usage: wgpu::BufferUsage::VERTEX,
+        });
+
+        let idx_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Blit Index Buffer"),
+            contents: bytemuck::cast_slice(INDICES),
+            usage: wgpu::BufferUsage::INDEX,
+        });
+
+        let indices = 0..INDICES.len() as u32;
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let texture_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStage::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStage::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler {
+                            comparison: false,
+                            filtering: false,
+                        },
+                        count: None,
+                    },
+                ],
+                label: Some("Blit Texture Bind Group Layout"),
+            });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Blit Render Pipeline Layout"),
+            bind_group_layouts: &[&texture_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Blit Render Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &quad_vert,
+                entry_point: "main",
+                buffers: &[LAYOUT],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &quad_frag,
+                entry_point: "main",
+                targets: &[wgpu::ColorTargetState {
+                    format,
+                    alpha_blend: wgpu::BlendState {
+                        src_factor: wgpu::BlendFactor::SrcAlpha,
+                        dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                    color_blend: wgpu::BlendState {
+                        src_factor: wgpu::BlendFactor::SrcAlpha,
+                        dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                    write_mask: wgpu::ColorWrite::ALL,
+                }],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: wgpu::CullMode::Back,
+                polygon_mode: wgpu::PolygonMode::Fill,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+        });
+
+        Blit {
+            pipeline,
+            vert_buffer,
+            idx_buffer,
+            indices,
+            texture_bind_group_layout,
+            sampler,
+        }
+    }
+
+    /// creates `size` sized textures to draw into with `sample_count` samples and resolve
+    /// into, ready to be blitted
+    pub fn create_offscreen(
+        &self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        size: (u32, u32),
+    ) -> Offscreen {
+        let extent = wgpu::Extent3d {
+            width: size.0,
+            height: size.1,
+            depth: 1,
+        };
+        let multisampled = device.create_texture(&wgpu::TextureDescriptor {
+            size: extent,
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
+            label: Some("Offscreen Multisampled Texture"),
+        });
+        let resolved = device.create_texture(&wgpu::TextureDescriptor {
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT | wgpu::TextureUsage::SAMPLED,
+            label: Some("Offscreen Resolved Texture"),
+        });
+        let multisampled = multisampled.create_view(&wgpu::TextureViewDescriptor::default());
+        let resolved = resolved.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.texture_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&resolved),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+            label: Some("Blit Texture Bind Group"),
+        });
+
+        Offscreen {
+            size,
+            multisampled,
+            resolved,
+            bind_group,
+        }
+    }
+
+    /// draws the resolved texture of `offscreen` over the pass's current viewport
+    pub fn render<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, offscreen: &'a Offscreen) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &offscreen.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vert_buffer.slice(..));
+        render_pass.set_index_buffer(self.idx_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        render_pass.draw_indexed(self.indices.clone(), 0, 0..1);
+    }
+}
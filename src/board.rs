@@ -1,7 +1,81 @@
+use std::fmt;
+
 use crate::piece::{Color, Piece, Type};
 
 pub type Board = [[Option<Piece>; 8]; 8];
 
+/// a square on the board, `file` 0 is the a-file and `rank` 0 is white's back rank
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Square {
+    pub file: usize,
+    pub rank: usize,
+}
+
+impl Square {
+    pub const fn new(file: usize, rank: usize) -> Square {
+        Square { file, rank }
+    }
+
+    /// parses algebraic notation such as `e4`
+    pub fn from_name(name: &str) -> Option<Square> {
+        let mut chars = name.chars();
+        let file = chars.next()?;
+        let rank = chars.next()?;
+        if chars.next().is_some() || !('a'..='h').contains(&file) || !('1'..='8').contains(&rank) {
+            return None;
+        }
+        Some(Square::new(
+            file as usize - 'a' as usize,
+            rank as usize - '1' as usize,
+        ))
+    }
+
+    /// the piece on this square, `Board` rows are stored from black's back rank down
+    pub fn piece(&self, board: &Board) -> Option<Piece> {
+        board[7 - self.rank][self.file]
+    }
+
+    pub fn set_piece(&self, board: &mut Board, piece: Option<Piece>) {
+        board[7 - self.rank][self.file] = piece;
+    }
+
+    /// where the square is drawn when viewed with `orientation`, i.e. rotated half a turn
+    /// when black is at the bottom
+    pub fn oriented(&self, orientation: Orientation) -> Square {
+        match orientation {
+            Orientation::White => *self,
+            Orientation::Black => Square::new(7 - self.file, 7 - self.rank),
+        }
+    }
+}
+
+/// which side's pieces are at the bottom of the view
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Orientation {
+    White,
+    Black,
+}
+
+impl Orientation {
+    pub fn flipped(&self) -> Orientation {
+        match self {
+            Orientation::White => Orientation::Black,
+            Orientation::Black => Orientation::White,
+        }
+    }
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            (b'a' + self.file as u8) as char,
+            (b'1' + self.rank as u8) as char
+        )
+    }
+}
+
 pub const fn default_board() -> Board {
     [
         [
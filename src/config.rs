@@ -0,0 +1,571 @@
+use std::{
+    collections::BTreeMap,
+    env, fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use thiserror::Error;
+use toml::{value::Table, Value};
+use toml_edit::{Document, Item, TableLike};
+
+use crate::{board::Orientation, theme::Theme};
+
+/// how long a move slides at an `animation_speed` of 1
+const ANIMATION_DURATION: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("unable to save {path}: {source}")]
+    Io { path: PathBuf, source: io::Error },
+    /// the file is left alone rather than replaced, as it may be halfway through an edit
+    #[error("{path} isn't valid toml, not saving over it: {source}")]
+    Invalid {
+        path: PathBuf,
+        source: toml_edit::TomlError,
+    },
+}
+
+/// the size and position of the window when it was last closed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowConfig {
+    pub width: u32,
+    pub height: u32,
+    /// the outer position, `None` leaves it to the window manager
+    pub position: Option<(i32, i32)>,
+}
+
+impl Default for WindowConfig {
+    fn default() -> WindowConfig {
+        WindowConfig {
+            width: 600,
+            height: 600,
+            position: None,
+        }
+    }
+}
+
+/// the viewer's settings, kept in a toml file like
+///
+/// ```toml
+/// theme = "green"
+/// pieces = "/home/me/pieces"
+/// orientation = "black"
+/// animation_speed = 1.5
+/// coordinates = true
+/// screenshot_dir = "/home/me/screenshots"
+///
+/// [window]
+/// width = 800
+/// height = 800
+/// x = 100
+/// y = 50
+///
+/// [keys]
+/// flip = "f"
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub window: WindowConfig,
+    /// the name of one of `Theme::PRESETS`
+    pub theme: String,
+    /// a directory of piece images or an atlas descriptor, `None` for the builtin pieces
+    pub pieces: Option<PathBuf>,
+    pub orientation: Orientation,
+    /// how much faster than normal moves slide, 0 turns animation off
+    pub animation_speed: f32,
+    /// whether the files and ranks are drawn along the edges of the board
+    pub coordinates: bool,
+    /// where screenshots are saved, relative to the working directory
+    pub screenshot_dir: PathBuf,
+    /// the key chords of actions by name, see `Keymap::from_config`
+    pub keys: BTreeMap<String, String>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            window: WindowConfig::default(),
+            theme: Theme::PRESETS[0].0.to_owned(),
+            pieces: None,
+            orientation: Orientation::White,
+            animation_speed: 1.0,
+            coordinates: true,
+            screenshot_dir: PathBuf::from("."),
+            keys: BTreeMap::new(),
+        }
+    }
+}
+
+impl Config {
+    /// `chessboard-rs/config.toml` in the xdg config directory, or in the roaming app data on
+    /// windows
+    pub fn default_path() -> Option<PathBuf> {
+        let dir = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .or_else(|| env::var_os("APPDATA").map(PathBuf::from))
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+        Some(dir.join("chessboard-rs").join("config.toml"))
+    }
+
+    /// reads the config at `path`, logging a warning for every setting that's invalid and
+    /// using its default instead. a missing file is the same as an empty one
+    pub fn load(path: &Path) -> Config {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Config::default(),
+            Err(err) => {
+                log::warn!(
+                    "unable to read {}, using the defaults: {}",
+                    path.display(),
+                    err
+                );
+                return Config::default();
+            }
+        };
+        let (config, warnings) = Config::parse(&text);
+        for warning in warnings {
+            log::warn!("{}: {}", path.display(), warning);
+        }
+        config
+    }
+
+    /// parses a config, returning what's wrong with each setting that fell back to its
+    /// default
+    pub fn parse(text: &str) -> (Config, Vec<String>) {
+        let mut config = Config::default();
+        let mut warnings = Vec::new();
+        let table = match text.parse::<Value>() {
+            Ok(Value::Table(table)) => table,
+            Ok(_) => unreachable!("a toml document is always a table"),
+            Err(err) => {
+                warnings.push(format!("invalid toml, using the defaults: {}", err));
+                return (config, warnings);
+            }
+        };
+
+        for (key, value) in &table {
+            let mut warn = |expected: &str| {
+                warnings.push(format!(
+                    "`{}` should be {}, using the default",
+                    key, expected
+                ));
+            };
+            match key.as_str() {
+                "window" => match value.as_table() {
+                    Some(window) => config.window = parse_window(window, &mut warnings),
+                    None => warn("a table"),
+                },
+                "theme" => match value.as_str().filter(|name| Theme::by_name(name).is_some()) {
+                    Some(name) => config.theme = name.to_owned(),
+                    None => {
+                        let names: Vec<_> = Theme::PRESETS.iter().map(|(name, _)| *name).collect();
+                        warn(&format!("one of {}", names.join(", ")));
+                    }
+                },
+                "pieces" => match value.as_str() {
+                    Some(path) => config.pieces = Some(PathBuf::from(path)),
+                    None => warn("a path"),
+                },
+                "orientation" => match value.as_str() {
+                    Some("white") => config.orientation = Orientation::White,
+                    Some("black") => config.orientation = Orientation::Black,
+                    _ => warn("\"white\" or \"black\""),
+                },
+                "animation_speed" => match as_f32(value).filter(|speed| *speed >= 0.0) {
+                    Some(speed) => config.animation_speed = speed,
+                    None => warn("a number of at least 0"),
+                },
+                "coordinates" => match value.as_bool() {
+                    Some(coordinates) => config.coordinates = coordinates,
+                    None => warn("true or false"),
+                },
+                "screenshot_dir" => match value.as_str() {
+                    Some(dir) => config.screenshot_dir = PathBuf::from(dir),
+                    None => warn("a path"),
+                },
+                "keys" => match value.as_table() {
+                    Some(keys) => {
+                        for (action, chord) in keys {
+                            match chord.as_str() {
+                                Some(chord) => {
+                                    config.keys.insert(action.clone(), chord.to_owned());
+                                }
+                                None => warnings.push(format!(
+                                    "the key of `{}` should be a string like \"ctrl+z\", ignoring it",
+                                    action
+                                )),
+                            }
+                        }
+                    }
+                    None => warn("a table"),
+                },
+                _ => warnings.push(format!("unknown setting `{}`, ignoring it", key)),
+            }
+        }
+        (config, warnings)
+    }
+
+    /// writes the settings that differ from `saved` into the config at `path`, creating it
+    /// and its directory if needed. everything else in the file is kept as it was, including
+    /// comments and settings that were invalid or unknown when it was loaded
+    pub fn save(&self, saved: &Config, path: &Path) -> Result<(), ConfigError> {
+        let io_error = |source| ConfigError::Io {
+            path: path.to_owned(),
+            source,
+        };
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(io_error(err)),
+        };
+        let mut document = text
+            .parse::<Document>()
+            .map_err(|source| ConfigError::Invalid {
+                path: path.to_owned(),
+                source,
+            })?;
+        self.update(saved, &mut document);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(io_error)?;
+        }
+        fs::write(path, document.to_string()).map_err(io_error)
+    }
+
+    /// sets the entries of `document` for the settings that differ from `saved`
+    fn update(&self, saved: &Config, document: &mut Document) {
+        let root = document.as_table_mut();
+        if self.window != saved.window {
+            let window = table_mut(root, "window");
+            if self.window.width != saved.window.width {
+                set(window, "width", i64::from(self.window.width));
+            }
+            if self.window.height != saved.window.height {
+                set(window, "height", i64::from(self.window.height));
+            }
+            if self.window.position != saved.window.position {
+                match self.window.position {
+                    Some((x, y)) => {
+                        set(window, "x", i64::from(x));
+                        set(window, "y", i64::from(y));
+                    }
+                    None => {
+                        window.remove("x");
+                        window.remove("y");
+                    }
+                }
+            }
+        }
+        if self.theme != saved.theme {
+            set(root, "theme", self.theme.as_str());
+        }
+        if self.pieces != saved.pieces {
+            match &self.pieces {
+                Some(pieces) => set(root, "pieces", pieces.to_string_lossy().as_ref()),
+                None => {
+                    root.remove("pieces");
+                }
+            }
+        }
+        if self.orientation != saved.orientation {
+            let orientation = match self.orientation {
+                Orientation::White => "white",
+                Orientation::Black => "black",
+            };
+            set(root, "orientation", orientation);
+        }
+        if self.animation_speed != saved.animation_speed {
+            set(root, "animation_speed", f64::from(self.animation_speed));
+        }
+        if self.coordinates != saved.coordinates {
+            set(root, "coordinates", self.coordinates);
+        }
+        if self.screenshot_dir != saved.screenshot_dir {
+            let screenshot_dir = self.screenshot_dir.to_string_lossy();
+            set(root, "screenshot_dir", screenshot_dir.as_ref());
+        }
+        if self.keys != saved.keys {
+            let keys = table_mut(root, "keys");
+            for (action, chord) in &self.keys {
+                if saved.keys.get(action) != Some(chord) {
+                    set(keys, action, chord.as_str());
+                }
+            }
+            for action in saved.keys.keys() {
+                if !self.keys.contains_key(action) {
+                    keys.remove(action);
+                }
+            }
+        }
+    }
+
+    /// the preset named by `theme`, which `parse` already checked
+    pub fn theme(&self) -> Theme {
+        Theme::by_name(&self.theme).unwrap_or_default()
+    }
+
+    /// how long a move slides for at `animation_speed`
+    pub fn animation_duration(&self) -> Duration {
+        if self.animation_speed > 0.0 {
+            ANIMATION_DURATION.div_f64(self.animation_speed.into())
+        } else {
+            Duration::from_secs(0)
+        }
+    }
+}
+
+fn parse_window(table: &Table, warnings: &mut Vec<String>) -> WindowConfig {
+    let mut window = WindowConfig::default();
+    let mut position = (None, None);
+    for (key, value) in table {
+        let mut warn = |expected: &str| {
+            warnings.push(format!(
+                "`window.{}` should be {}, using the default",
+                key, expected
+            ));
+        };
+        match key.as_str() {
+            "width" | "height" => {
+                let size = value
+                    .as_integer()
+                    .filter(|&size| size > 0 && size <= u32::MAX as i64);
+                match (key.as_str(), size) {
+                    ("width", Some(width)) => window.width = width as u32,
+                    ("height", Some(height)) => window.height = height as u32,
+                    _ => warn("a positive number of pixels"),
+                }
+            }
+            "x" | "y" => {
+                let coordinate = value
+                    .as_integer()
+                    .filter(|&c| c >= i32::MIN as i64 && c <= i32::MAX as i64);
+                match (key.as_str(), coordinate) {
+                    ("x", Some(x)) => position.0 = Some(x as i32),
+                    ("y", Some(y)) => position.1 = Some(y as i32),
+                    _ => warn("a number of pixels"),
+                }
+            }
+            _ => warnings.push(format!("unknown setting `window.{}`, ignoring it", key)),
+        }
+    }
+    match position {
+        (Some(x), Some(y)) => window.position = Some((x, y)),
+        (None, None) => {}
+        _ => warnings.push("`window` needs both an `x` and a `y` to be placed".to_owned()),
+    }
+    window
+}
+
+/// the table `key` of `table`, replacing anything else that's there with an empty one
+fn table_mut<'a>(table: &'a mut dyn TableLike, key: &str) -> &'a mut dyn TableLike {
+    if !matches!(table.get(key), Some(item) if item.is_table_like()) {
+        table.insert(key, toml_edit::table());
+    }
+    table
+        .get_mut(key)
+        .and_then(Item::as_table_like_mut)
+        .unwrap()
+}
+
+/// sets `key` of `table` to `value`, keeping the comment after the value it replaces
+fn set(table: &mut dyn TableLike, key: &str, value: impl Into<toml_edit::Value>) {
+    let mut value = value.into();
+    match table.get_mut(key) {
+        Some(Item::Value(old)) => {
+            *value.decor_mut() = old.decor().clone();
+            *old = value;
+        }
+        _ => {
+            table.insert(key, Item::Value(value));
+        }
+    }
+}
+
+/// toml keeps integers apart from floats, but `2` is as good a speed as `2.0`
+fn as_f32(value: &Value) -> Option<f32> {
+    match value {
+        Value::Float(float) if float.is_finite() => Some(*float as f32),
+        Value::Integer(integer) => Some(*integer as f32),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a path in a directory of its own under the temp directory, which doesn't exist yet
+    fn temp_path(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!(
+            "chessboard-rs-config-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir.join("config.toml")
+    }
+
+    #[test]
+    fn parse_empty() {
+        assert_eq!(Config::parse(""), (Config::default(), Vec::new()));
+    }
+
+    #[test]
+    fn parse_settings() {
+        let (config, warnings) = Config::parse(
+            r#"
+theme = "green"
+pieces = "/home/me/pieces"
+orientation = "black"
+animation_speed = 2
+coordinates = false
+screenshot_dir = "shots"
+
+[window]
+width = 800
+height = 700
+x = -10
+y = 50
+
+[keys]
+flip = "ctrl+f"
+"#,
+        );
+        assert_eq!(warnings, Vec::<String>::new());
+        assert_eq!(
+            config.window,
+            WindowConfig {
+                width: 800,
+                height: 700,
+                position: Some((-10, 50)),
+            }
+        );
+        assert_eq!(config.theme, "green");
+        assert_eq!(config.pieces, Some(PathBuf::from("/home/me/pieces")));
+        assert_eq!(config.orientation, Orientation::Black);
+        assert_eq!(config.animation_duration(), Duration::from_millis(100));
+        assert!(!config.coordinates);
+        assert_eq!(config.screenshot_dir, PathBuf::from("shots"));
+        assert_eq!(config.keys.get("flip").map(String::as_str), Some("ctrl+f"));
+    }
+
+    #[test]
+    fn invalid_settings_fall_back() {
+        let (config, warnings) = Config::parse(
+            r#"
+theme = "gren"
+orientation = "sideways"
+animation_speed = -1
+coordinates = "yes"
+colour = "red"
+window = 5
+
+[keys]
+flip = 3
+undo = "ctrl+z"
+"#,
+        );
+        let expected = Config {
+            keys: vec![("undo".to_owned(), "ctrl+z".to_owned())]
+                .into_iter()
+                .collect(),
+            ..Config::default()
+        };
+        assert_eq!(config, expected);
+        assert_eq!(warnings.len(), 7, "{:?}", warnings);
+        for key in &[
+            "`theme`",
+            "`orientation`",
+            "`animation_speed`",
+            "`coordinates`",
+            "`colour`",
+            "`window`",
+            "`flip`",
+        ] {
+            assert!(
+                warnings.iter().any(|warning| warning.contains(key)),
+                "no warning about {} in {:?}",
+                key,
+                warnings
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_window() {
+        let (config, warnings) = Config::parse("[window]\nwidth = 0\nheight = 500\nx = 10\n");
+        assert_eq!(
+            config.window,
+            WindowConfig {
+                height: 500,
+                ..WindowConfig::default()
+            }
+        );
+        assert_eq!(warnings.len(), 2, "{:?}", warnings);
+        assert!(warnings[0].contains("`window.width`"));
+        assert!(warnings[1].contains("`x` and a `y`"));
+    }
+
+    #[test]
+    fn invalid_toml() {
+        let (config, warnings) = Config::parse("theme = ");
+        assert_eq!(config, Config::default());
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn save_keeps_the_rest_of_the_file() {
+        let path = temp_path("keep");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let text = "# my settings\ntheme = \"gren\"\ncolour = \"red\"\n\n[window]\nwidth = 800 # wide\nheight = 800\n\n[keys]\nflip = \"g\"\nundo = \"u\"\n";
+        fs::write(&path, text).unwrap();
+
+        let saved = Config::load(&path);
+        let mut config = saved.clone();
+        config.orientation = Orientation::Black;
+        config.window.width = 900;
+        config.window.position = Some((1, 2));
+        config.keys.remove("undo");
+        config.keys.insert("redo".to_owned(), "r".to_owned());
+        config.save(&saved, &path).unwrap();
+
+        let written = fs::read_to_string(&path).unwrap();
+        assert!(written.starts_with("# my settings\ntheme = \"gren\"\ncolour = \"red\"\n"));
+        assert!(written.contains("width = 900 # wide\nheight = 800\n"));
+        assert!(written.contains("flip = \"g\"\n"));
+        assert!(!written.contains("undo"));
+        let (reloaded, _) = Config::parse(&written);
+        assert_eq!(reloaded, config);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn save_creates_the_file() {
+        let path = temp_path("create");
+        let config = Config {
+            theme: "blue".to_owned(),
+            ..Config::default()
+        };
+        config.save(&Config::default(), &path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "theme = \"blue\"\n");
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn save_leaves_invalid_toml_alone() {
+        let path = temp_path("invalid");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "theme = ").unwrap();
+        let config = Config {
+            coordinates: false,
+            ..Config::default()
+        };
+        assert!(matches!(
+            config.save(&Config::default(), &path),
+            Err(ConfigError::Invalid { .. })
+        ));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "theme = ");
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
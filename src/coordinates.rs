@@ -0,0 +1,323 @@
+use image::{Rgba, RgbaImage};
+use wgpu::util::DeviceExt;
+
+use crate::{
+    atlas::linear_to_srgb,
+    board::{Board, Orientation, Square},
+    quad::{INDICES, LAYOUT, VERTICES},
+    renderable::{LayerContext, Renderable},
+    theme::Theme,
+};
+
+/// height of the labels as a fraction of a square
+const COORDINATE_SIZE: f32 = 0.25;
+
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+
+/// 5x7 bitmaps of the files a to h, one byte per row with the leftmost pixel in bit 4, so the
+/// labels don't need a font
+#[rustfmt::skip]
+const FILES: [[u8; 7]; 8] = [
+    [0b00000, 0b00000, 0b01110, 0b00001, 0b01111, 0b10001, 0b01111],
+    [0b10000, 0b10000, 0b10110, 0b11001, 0b10001, 0b10001, 0b11110],
+    [0b00000, 0b00000, 0b01110, 0b10000, 0b10000, 0b10001, 0b01110],
+    [0b00001, 0b00001, 0b01101, 0b10011, 0b10001, 0b10001, 0b01111],
+    [0b00000, 0b00000, 0b01110, 0b10001, 0b11111, 0b10000, 0b01110],
+    [0b00110, 0b01001, 0b01000, 0b11100, 0b01000, 0b01000, 0b01000],
+    [0b00000, 0b01111, 0b10001, 0b10001, 0b01111, 0b00001, 0b01110],
+    [0b10000, 0b10000, 0b10110, 0b11001, 0b10001, 0b10001, 0b10001],
+];
+
+/// the ranks 1 to 8, laid out like `FILES`
+#[rustfmt::skip]
+const RANKS: [[u8; 7]; 8] = [
+    [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+    [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111],
+    [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110],
+    [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010],
+    [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110],
+    [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110],
+    [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000],
+    [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110],
+];
+
+/// calls `plot` with every pixel of the labels on a `size`x`size` board and its linear color,
+/// the ranks in the top left corner of the left column and the files in the bottom right
+/// corner of the bottom row, each in the color of the other kind of square
+pub fn plot_coordinates(
+    size: u32,
+    theme: &Theme,
+    orientation: Orientation,
+    mut plot: impl FnMut(u32, u32, [f32; 3]),
+) {
+    let square_size = size as f32 / 8.0;
+    let scale = ((square_size * COORDINATE_SIZE / GLYPH_HEIGHT as f32).round() as u32).max(1);
+    let margin = scale * 2;
+
+    let mut draw = |glyph: &[u8; 7], left: u32, top: u32, color: [f32; 4]| {
+        for (row, bits) in glyph.iter().enumerate() {
+            for column in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - column)) == 0 {
+                    continue;
+                }
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        let x = left + column * scale + dx;
+                        let y = top + row as u32 * scale + dy;
+                        if x < size && y < size {
+                            plot(x, y, [color[0], color[1], color[2]]);
+                        }
+                    }
+                }
+            }
+        }
+    };
+
+    for i in 0..8 {
+        let rank = Square::new(0, 7 - i).oriented(orientation).rank;
+        let top = (i as f32 * square_size) as u32 + margin;
+        draw(
+            &RANKS[rank],
+            margin,
+            top,
+            theme.square_color(Square::new(1, 7 - i)),
+        );
+
+        let file = Square::new(i, 0).oriented(orientation).file;
+        let right = ((i + 1) as f32 * square_size) as u32;
+        let bottom = size;
+        draw(
+            &FILES[file],
+            right.saturating_sub(margin + GLYPH_WIDTH * scale),
+            bottom.saturating_sub(margin + GLYPH_HEIGHT * scale),
+            theme.square_color(Square::new(i, 1)),
+        );
+    }
+}
+
+/// draws the files and ranks along the edges of the board from a texture, which is drawn
+/// again on the cpu whenever the board is resized, flipped or recolored
+pub struct CoordinatesView {
+    pipeline: wgpu::RenderPipeline,
+    vert_buffer: wgpu::Buffer,
+    idx_buffer: wgpu::Buffer,
+    idx_num: u32,
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    bind_group: Option<wgpu::BindGroup>,
+    theme: Theme,
+    size: u32,
+    /// what the texture was last drawn with, so it's only drawn again when something changed
+    drawn: Option<(Theme, Orientation, u32)>,
+}
+
+impl CoordinatesView {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> CoordinatesView {
+        let quad_vert =
+            device.create_shader_module(&wgpu::include_spirv!("shaders/board.vert.spv"));
+        let quad_frag = device.create_shader_module(&wgpu::include_spirv!("shaders/blit.frag.spv"));
+
+        let vert_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Coordinates Vertex Buffer"),
+            contents: bytemuck::cast_slice(VERTICES),
+            usage: wgpu::BufferUsage::VERTEX,
+        });
+
+        let idx_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Coordinates Index Buffer"),
+            contents: bytemuck::cast_slice(INDICES),
+            usage: wgpu::BufferUsage::INDEX,
+        });
+
+        // the texture is exactly the size of the board so every texel lands on one pixel
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let texture_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStage::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStage::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler {
+                            comparison: false,
+                            filtering: false,
+                        },
+                        count: None,
+                    },
+                ],
+                label: Some("Coordinates Texture Bind Group Layout"),
+            });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Coordinates Render Pipeline Layout"),
+            bind_group_layouts: &[&texture_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Coordinates Render Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &quad_vert,
+                entry_point: "main",
+                buffers: &[LAYOUT],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &quad_frag,
+                entry_point: "main",
+                targets: &[wgpu::ColorTargetState {
+                    format,
+                    alpha_blend: wgpu::BlendState {
+                        src_factor: wgpu::BlendFactor::SrcAlpha,
+                        dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                    color_blend: wgpu::BlendState {
+                        src_factor: wgpu::BlendFactor::SrcAlpha,
+                        dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                    write_mask: wgpu::ColorWrite::ALL,
+                }],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: wgpu::CullMode::Back,
+                polygon_mode: wgpu::PolygonMode::Fill,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+        });
+
+        CoordinatesView {
+            pipeline,
+            vert_buffer,
+            idx_buffer,
+            idx_num: INDICES.len() as u32,
+            texture_bind_group_layout,
+            sampler,
+            bind_group: None,
+            theme: Theme::default(),
+            size: 1,
+            drawn: None,
+        }
+    }
+
+    /// the labels take the colors of the squares, so they follow the theme
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    /// draws the labels into a new texture for a board of `size` pixels
+    fn upload(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        orientation: Orientation,
+        size: u32,
+    ) {
+        let mut image = RgbaImage::new(size, size);
+        plot_coordinates(size, &self.theme, orientation, |x, y, [r, g, b]| {
+            let srgb = [linear_to_srgb(r), linear_to_srgb(g), linear_to_srgb(b), 255];
+            image.put_pixel(x, y, Rgba(srgb));
+        });
+
+        let extent = wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth: 1,
+        };
+        let texture = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                size: extent,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+                label: Some("Coordinates Texture"),
+            },
+            &image,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        self.bind_group = Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.texture_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+            label: Some("Coordinates Texture Bind Group"),
+        }));
+        self.drawn = Some((self.theme, orientation, size));
+    }
+
+    /// draws the texture again if the board changed size, color or orientation
+    pub fn prepare_texture(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        orientation: Orientation,
+    ) {
+        let size = self.size;
+        if self.drawn != Some((self.theme, orientation, size)) {
+            self.upload(device, queue, orientation, size);
+        }
+    }
+}
+
+impl Renderable for CoordinatesView {
+    /// the texture needs the device as well, see `prepare_texture`
+    fn prepare(&mut self, _queue: &wgpu::Queue, _board: &Board, _orientation: Orientation) {}
+
+    fn render<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if let Some(bind_group) = &self.bind_group {
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(0, bind_group, &[]);
+            render_pass.set_vertex_buffer(0, self.vert_buffer.slice(..));
+            render_pass.set_index_buffer(self.idx_buffer.slice(..), wgpu::IndexFormat::Uint16);
+            render_pass.draw_indexed(0..self.idx_num, 0, 0..1);
+        }
+    }
+
+    fn resize(&mut self, context: &LayerContext) {
+        let viewport = context.viewport;
+        self.size = viewport.width.min(viewport.height).max(1.0) as u32;
+    }
+}
@@ -0,0 +1,22 @@
+use thiserror::Error;
+
+use crate::piece_set::PieceSetError;
+
+/// why a `BoardView` or `BoardRenderer` couldn't be created
+#[derive(Debug, Error)]
+pub enum Error {
+    /// none of the requested backends has an adapter that can draw to the window, try other
+    /// backends or draw without a gpu
+    #[error("no graphics adapter is compatible with the window")]
+    NoAdapter,
+    #[error("unable to open the graphics device: {0}")]
+    RequestDevice(#[from] wgpu::RequestDeviceError),
+    /// the board can only be drawn into 8 bit rgba and bgra targets
+    #[error("{0:?} targets aren't supported")]
+    UnsupportedFormat(wgpu::TextureFormat),
+    /// wgpu only guarantees 1 and 4 samples per pixel on every adapter
+    #[error("{0} samples per pixel aren't supported, use 1 or 4")]
+    UnsupportedSampleCount(u32),
+    #[error("unable to load the piece images: {0}")]
+    Asset(#[from] PieceSetError),
+}
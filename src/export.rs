@@ -0,0 +1,294 @@
+use std::{io::Write, time::Duration};
+
+use image::{
+    gif::{GifEncoder, Repeat},
+    Delay, Frame, RgbaImage,
+};
+use thiserror::Error;
+
+use crate::{
+    animation::ease_in_out_cubic,
+    board::Board,
+    rules::{Move, Position},
+    software::SoftwareRenderer,
+    theme::Highlight,
+    Renderer,
+};
+
+/// the shortest delay gif viewers honour, browsers show frames of 10ms or less for 100ms
+const MIN_GIF_DELAY: Duration = Duration::from_millis(20);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    Gif,
+    /// animated png, lossless but larger
+    Apng,
+}
+
+/// how `export_game` draws and times the frames
+#[derive(Debug, Clone, Copy)]
+pub struct ExportOptions {
+    pub format: Format,
+    /// width and height of the frames in pixels
+    pub size: u32,
+    /// how long each position is shown
+    pub frame_delay: Duration,
+    /// the frames drawn while a piece slides to its square, 0 jumps straight to the next
+    /// position. gif frames are shown for at least 20ms, so more frames than fit in
+    /// `tween_duration` make the slide take longer
+    pub tween_frames: u32,
+    /// how long the slide of each move takes
+    pub tween_duration: Duration,
+    /// highlights the squares of the move that led to each position
+    pub highlight_last_move: bool,
+}
+
+impl Default for ExportOptions {
+    fn default() -> ExportOptions {
+        ExportOptions {
+            format: Format::Gif,
+            size: 400,
+            frame_delay: Duration::from_secs(1),
+            tween_frames: 0,
+            tween_duration: Duration::from_millis(200),
+            highlight_last_move: true,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ExportError {
+    #[error("move {ply} ({from}{to}) isn't legal")]
+    IllegalMove {
+        /// counted from 1
+        ply: usize,
+        from: String,
+        to: String,
+    },
+    #[error("unable to encode the gif: {0}")]
+    Gif(#[from] image::ImageError),
+    #[error("unable to encode the png: {0}")]
+    Png(#[from] png::EncodingError),
+}
+
+/// one frame of the animation, kept until it's drawn so only one image is in memory at a time
+struct Step {
+    old: Board,
+    new: Board,
+    /// eased progress from `old` to `new`
+    progress: f32,
+    /// the move from `old` to `new`, `None` for the start position
+    mv: Option<Move>,
+    delay: Duration,
+}
+
+/// plays `moves` from `start` and writes every position as a frame of an animation, drawn
+/// with the theme, orientation and pieces of `renderer`. the last move highlight of
+/// `renderer` is cleared for the start position and overwritten after each move when
+/// `highlight_last_move` is set. nothing is written if a move is illegal
+pub fn export_game(
+    renderer: &mut SoftwareRenderer,
+    start: &Position,
+    moves: &[Move],
+    options: &ExportOptions,
+    writer: impl Write,
+) -> Result<(), ExportError> {
+    let size = options.size.max(1);
+    let steps = steps(start, moves, options)?;
+    let count = steps.len();
+    let frames = steps.into_iter().map(|step| {
+        if options.highlight_last_move {
+            match step.mv {
+                Some(mv) => renderer.set_highlight(Highlight::LastMove, &[mv.from, mv.to]),
+                None => renderer.set_highlight(Highlight::LastMove, &[]),
+            }
+        }
+        let image = renderer.render_transition(&step.old, &step.new, step.progress, size);
+        (image, step.delay)
+    });
+
+    match options.format {
+        Format::Gif => write_gif(writer, frames),
+        Format::Apng => write_apng(writer, size, count, frames),
+    }
+}
+
+/// the start position, then the slide and resulting position of every move
+fn steps(
+    start: &Position,
+    moves: &[Move],
+    options: &ExportOptions,
+) -> Result<Vec<Step>, ExportError> {
+    let tween_delay = options.tween_duration / (options.tween_frames + 1);
+    let mut position = *start;
+    let mut steps = vec![Step {
+        old: position.board,
+        new: position.board,
+        progress: 1.0,
+        mv: None,
+        delay: options.frame_delay,
+    }];
+    for (i, &mv) in moves.iter().enumerate() {
+        let old = position.board;
+        if !position.play(mv) {
+            return Err(ExportError::IllegalMove {
+                ply: i + 1,
+                from: mv.from.to_string(),
+                to: mv.to.to_string(),
+            });
+        }
+        for frame in 1..=options.tween_frames {
+            steps.push(Step {
+                old,
+                new: position.board,
+                progress: ease_in_out_cubic(frame as f32 / (options.tween_frames + 1) as f32),
+                mv: Some(mv),
+                delay: tween_delay,
+            });
+        }
+        steps.push(Step {
+            old: position.board,
+            new: position.board,
+            progress: 1.0,
+            mv: Some(mv),
+            delay: options.frame_delay,
+        });
+    }
+    Ok(steps)
+}
+
+fn write_gif(
+    writer: impl Write,
+    frames: impl Iterator<Item = (RgbaImage, Duration)>,
+) -> Result<(), ExportError> {
+    // the default speed of 1 takes seconds per frame for little visible gain
+    let mut encoder = GifEncoder::new_with_speed(writer, 10);
+    encoder.set_repeat(Repeat::Infinite)?;
+    encoder.encode_frames(frames.map(|(image, delay)| {
+        let delay = Delay::from_saturating_duration(delay.max(MIN_GIF_DELAY));
+        Frame::from_parts(image, 0, 0, delay)
+    }))?;
+    Ok(())
+}
+
+/// apng declares its frame count up front, so it's given along with the frames
+fn write_apng(
+    writer: impl Write,
+    size: u32,
+    count: usize,
+    frames: impl Iterator<Item = (RgbaImage, Duration)>,
+) -> Result<(), ExportError> {
+    let mut encoder = png::Encoder::new(writer, size, size);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(count as u32, 0)?;
+    let mut writer = encoder.write_header()?;
+    for (image, delay) in frames {
+        let millis = delay.as_millis().min(u16::MAX as u128) as u16;
+        writer.set_frame_delay(millis, 1000)?;
+        writer.write_image_data(&image)?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use image::{gif::GifDecoder, AnimationDecoder};
+
+    use super::*;
+    use crate::board::Square;
+
+    fn square(name: &str) -> Square {
+        Square::from_name(name).unwrap()
+    }
+
+    fn two_plies() -> Vec<Move> {
+        vec![
+            Move::new(square("e2"), square("e4")),
+            Move::new(square("e7"), square("e5")),
+        ]
+    }
+
+    fn options(format: Format) -> ExportOptions {
+        ExportOptions {
+            format,
+            size: 32,
+            frame_delay: Duration::from_millis(500),
+            tween_frames: 3,
+            // 10ms a frame, shorter than gifs can show
+            tween_duration: Duration::from_millis(40),
+            highlight_last_move: true,
+        }
+    }
+
+    fn export(options: &ExportOptions, moves: &[Move]) -> Result<Vec<u8>, ExportError> {
+        let mut renderer = SoftwareRenderer::new().unwrap();
+        let mut output = Vec::new();
+        export_game(
+            &mut renderer,
+            &Position::default(),
+            moves,
+            options,
+            &mut output,
+        )?;
+        Ok(output)
+    }
+
+    #[test]
+    fn gif() {
+        let output = export(&options(Format::Gif), &two_plies()).unwrap();
+        let frames = GifDecoder::new(Cursor::new(output))
+            .unwrap()
+            .into_frames()
+            .collect_frames()
+            .unwrap();
+        assert_eq!(frames.len(), 1 + 2 * (3 + 1));
+        for frame in &frames {
+            assert_eq!(frame.buffer().dimensions(), (32, 32));
+            let (numer, denom) = frame.delay().numer_denom_ms();
+            assert!(
+                Duration::from_millis((numer / denom) as u64) >= MIN_GIF_DELAY,
+                "{}/{}ms",
+                numer,
+                denom
+            );
+        }
+        let (numer, denom) = frames[0].delay().numer_denom_ms();
+        assert_eq!(numer / denom, 500);
+    }
+
+    #[test]
+    fn apng() {
+        let output = export(&options(Format::Apng), &two_plies()).unwrap();
+        let mut reader = png::Decoder::new(Cursor::new(output)).read_info().unwrap();
+        let frames = reader.info().animation_control.unwrap().num_frames;
+        assert_eq!(frames, 1 + 2 * (3 + 1));
+
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let mut delays = Vec::new();
+        for _ in 0..frames {
+            reader.next_frame(&mut buffer).unwrap();
+            let control = reader.info().frame_control.unwrap();
+            delays.push((control.delay_num, control.delay_den));
+        }
+        // apng has no minimum delay
+        assert_eq!(delays[0], (500, 1000));
+        assert_eq!(delays[1], (10, 1000));
+        assert_eq!(delays[4], (500, 1000));
+    }
+
+    #[test]
+    fn illegal_moves_write_nothing() {
+        let mut moves = two_plies();
+        moves.push(Move::new(square("e4"), square("e5")));
+        match export(&options(Format::Gif), &moves) {
+            Err(ExportError::IllegalMove { ply: 3, from, to }) => {
+                assert_eq!((from.as_str(), to.as_str()), ("e4", "e5"));
+            }
+            other => panic!("{:?}", other.map(|output| output.len())),
+        }
+    }
+}
@@ -0,0 +1,533 @@
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use thiserror::Error;
+use winit::event::{ModifiersState, VirtualKeyCode};
+
+/// what a key press can do in the viewer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Action {
+    FlipBoard,
+    /// takes back the move that led to the shown position, along with any after it
+    Undo,
+    /// plays the moves last taken back again
+    Redo,
+    /// steps through the game without changing it
+    NextMove,
+    PreviousMove,
+    FirstMove,
+    LastMove,
+    ToggleCoordinates,
+    NextTheme,
+    Screenshot,
+    Quit,
+}
+
+impl Action {
+    pub const ALL: [Action; 11] = [
+        Action::FlipBoard,
+        Action::Undo,
+        Action::Redo,
+        Action::NextMove,
+        Action::PreviousMove,
+        Action::FirstMove,
+        Action::LastMove,
+        Action::ToggleCoordinates,
+        Action::NextTheme,
+        Action::Screenshot,
+        Action::Quit,
+    ];
+
+    /// the name the action is bound by in the config
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::FlipBoard => "flip",
+            Action::Undo => "undo",
+            Action::Redo => "redo",
+            Action::NextMove => "next-move",
+            Action::PreviousMove => "previous-move",
+            Action::FirstMove => "first-move",
+            Action::LastMove => "last-move",
+            Action::ToggleCoordinates => "toggle-coordinates",
+            Action::NextTheme => "next-theme",
+            Action::Screenshot => "screenshot",
+            Action::Quit => "quit",
+        }
+    }
+
+    pub fn by_name(name: &str) -> Option<Action> {
+        Action::ALL
+            .iter()
+            .copied()
+            .find(|action| action.name().eq_ignore_ascii_case(name))
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// the names keys are written with, the first name of each key is the one it's displayed as
+const KEYS: &[(&str, VirtualKeyCode)] = &[
+    ("a", VirtualKeyCode::A),
+    ("b", VirtualKeyCode::B),
+    ("c", VirtualKeyCode::C),
+    ("d", VirtualKeyCode::D),
+    ("e", VirtualKeyCode::E),
+    ("f", VirtualKeyCode::F),
+    ("g", VirtualKeyCode::G),
+    ("h", VirtualKeyCode::H),
+    ("i", VirtualKeyCode::I),
+    ("j", VirtualKeyCode::J),
+    ("k", VirtualKeyCode::K),
+    ("l", VirtualKeyCode::L),
+    ("m", VirtualKeyCode::M),
+    ("n", VirtualKeyCode::N),
+    ("o", VirtualKeyCode::O),
+    ("p", VirtualKeyCode::P),
+    ("q", VirtualKeyCode::Q),
+    ("r", VirtualKeyCode::R),
+    ("s", VirtualKeyCode::S),
+    ("t", VirtualKeyCode::T),
+    ("u", VirtualKeyCode::U),
+    ("v", VirtualKeyCode::V),
+    ("w", VirtualKeyCode::W),
+    ("x", VirtualKeyCode::X),
+    ("y", VirtualKeyCode::Y),
+    ("z", VirtualKeyCode::Z),
+    ("0", VirtualKeyCode::Key0),
+    ("1", VirtualKeyCode::Key1),
+    ("2", VirtualKeyCode::Key2),
+    ("3", VirtualKeyCode::Key3),
+    ("4", VirtualKeyCode::Key4),
+    ("5", VirtualKeyCode::Key5),
+    ("6", VirtualKeyCode::Key6),
+    ("7", VirtualKeyCode::Key7),
+    ("8", VirtualKeyCode::Key8),
+    ("9", VirtualKeyCode::Key9),
+    ("f1", VirtualKeyCode::F1),
+    ("f2", VirtualKeyCode::F2),
+    ("f3", VirtualKeyCode::F3),
+    ("f4", VirtualKeyCode::F4),
+    ("f5", VirtualKeyCode::F5),
+    ("f6", VirtualKeyCode::F6),
+    ("f7", VirtualKeyCode::F7),
+    ("f8", VirtualKeyCode::F8),
+    ("f9", VirtualKeyCode::F9),
+    ("f10", VirtualKeyCode::F10),
+    ("f11", VirtualKeyCode::F11),
+    ("f12", VirtualKeyCode::F12),
+    ("escape", VirtualKeyCode::Escape),
+    ("esc", VirtualKeyCode::Escape),
+    ("space", VirtualKeyCode::Space),
+    ("enter", VirtualKeyCode::Return),
+    ("return", VirtualKeyCode::Return),
+    ("tab", VirtualKeyCode::Tab),
+    ("backspace", VirtualKeyCode::Back),
+    ("delete", VirtualKeyCode::Delete),
+    ("insert", VirtualKeyCode::Insert),
+    ("home", VirtualKeyCode::Home),
+    ("end", VirtualKeyCode::End),
+    ("pageup", VirtualKeyCode::PageUp),
+    ("pagedown", VirtualKeyCode::PageDown),
+    ("left", VirtualKeyCode::Left),
+    ("right", VirtualKeyCode::Right),
+    ("up", VirtualKeyCode::Up),
+    ("down", VirtualKeyCode::Down),
+    ("minus", VirtualKeyCode::Minus),
+    ("equals", VirtualKeyCode::Equals),
+    ("comma", VirtualKeyCode::Comma),
+    ("period", VirtualKeyCode::Period),
+    ("slash", VirtualKeyCode::Slash),
+    ("backslash", VirtualKeyCode::Backslash),
+    ("semicolon", VirtualKeyCode::Semicolon),
+    ("apostrophe", VirtualKeyCode::Apostrophe),
+    ("grave", VirtualKeyCode::Grave),
+    ("lbracket", VirtualKeyCode::LBracket),
+    ("rbracket", VirtualKeyCode::RBracket),
+];
+
+/// the modifiers in the order they're displayed, with their names
+const MODIFIERS: &[(&str, ModifiersState)] = &[
+    ("ctrl", ModifiersState::CTRL),
+    ("control", ModifiersState::CTRL),
+    ("shift", ModifiersState::SHIFT),
+    ("alt", ModifiersState::ALT),
+    ("logo", ModifiersState::LOGO),
+    ("super", ModifiersState::LOGO),
+    ("cmd", ModifiersState::LOGO),
+];
+
+/// a key along with the modifiers held down with it, written like `ctrl+shift+z`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub key: VirtualKeyCode,
+    pub modifiers: ModifiersState,
+}
+
+impl KeyChord {
+    pub fn new(key: VirtualKeyCode, modifiers: ModifiersState) -> KeyChord {
+        KeyChord { key, modifiers }
+    }
+
+    /// a key pressed on its own
+    pub fn key(key: VirtualKeyCode) -> KeyChord {
+        KeyChord::new(key, ModifiersState::empty())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ChordError {
+    #[error("no key in {0:?}")]
+    MissingKey(String),
+    #[error("unknown key {0:?}")]
+    UnknownKey(String),
+    #[error("{0:?} has more than one key, only modifiers can be combined")]
+    TooManyKeys(String),
+}
+
+impl FromStr for KeyChord {
+    type Err = ChordError;
+
+    fn from_str(chord: &str) -> Result<KeyChord, ChordError> {
+        let mut modifiers = ModifiersState::empty();
+        let mut key = None;
+        for part in chord.split('+').map(str::trim) {
+            let part = part.to_ascii_lowercase();
+            if let Some(&(_, modifier)) = MODIFIERS.iter().find(|(name, _)| *name == part) {
+                modifiers |= modifier;
+                continue;
+            }
+            let code = KEYS
+                .iter()
+                .find(|(name, _)| *name == part)
+                .map(|&(_, code)| code)
+                .ok_or_else(|| ChordError::UnknownKey(part.clone()))?;
+            if key.replace(code).is_some() {
+                return Err(ChordError::TooManyKeys(chord.to_owned()));
+            }
+        }
+        let key = key.ok_or_else(|| ChordError::MissingKey(chord.to_owned()))?;
+        Ok(KeyChord { key, modifiers })
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut shown = ModifiersState::empty();
+        for &(name, modifier) in MODIFIERS {
+            if self.modifiers.contains(modifier) && !shown.contains(modifier) {
+                write!(f, "{}+", name)?;
+                shown |= modifier;
+            }
+        }
+        match KEYS.iter().find(|&&(_, code)| code == self.key) {
+            Some((name, _)) => f.write_str(name),
+            None => write!(f, "{:?}", self.key),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum KeymapError {
+    #[error("unknown action {0:?}, the actions are {}", action_names())]
+    UnknownAction(String),
+    #[error("invalid key for {action}: {source}")]
+    Chord { action: Action, source: ChordError },
+    #[error("{chord} is bound to both {kept} and {dropped}, keeping it for {kept}")]
+    Conflict {
+        chord: KeyChord,
+        kept: Action,
+        dropped: Action,
+    },
+}
+
+fn action_names() -> String {
+    let names: Vec<_> = Action::ALL.iter().map(Action::name).collect();
+    names.join(", ")
+}
+
+/// which action each key chord triggers, a chord triggers at most one action but an action
+/// can have several chords
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keymap {
+    bindings: BTreeMap<Action, Vec<KeyChord>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        let ctrl = ModifiersState::CTRL;
+        let bindings = vec![
+            (Action::FlipBoard, vec![KeyChord::key(VirtualKeyCode::F)]),
+            (Action::Undo, vec![KeyChord::new(VirtualKeyCode::Z, ctrl)]),
+            (
+                Action::Redo,
+                vec![
+                    KeyChord::new(VirtualKeyCode::Y, ctrl),
+                    KeyChord::new(VirtualKeyCode::Z, ctrl | ModifiersState::SHIFT),
+                ],
+            ),
+            (Action::NextMove, vec![KeyChord::key(VirtualKeyCode::Right)]),
+            (
+                Action::PreviousMove,
+                vec![KeyChord::key(VirtualKeyCode::Left)],
+            ),
+            (Action::FirstMove, vec![KeyChord::key(VirtualKeyCode::Home)]),
+            (Action::LastMove, vec![KeyChord::key(VirtualKeyCode::End)]),
+            (
+                Action::ToggleCoordinates,
+                vec![KeyChord::key(VirtualKeyCode::C)],
+            ),
+            (Action::NextTheme, vec![KeyChord::key(VirtualKeyCode::T)]),
+            (Action::Screenshot, vec![KeyChord::key(VirtualKeyCode::F12)]),
+            (
+                Action::Quit,
+                vec![
+                    KeyChord::key(VirtualKeyCode::Escape),
+                    KeyChord::new(VirtualKeyCode::Q, ctrl),
+                ],
+            ),
+        ];
+        Keymap {
+            bindings: bindings.into_iter().collect(),
+        }
+    }
+}
+
+impl Keymap {
+    /// a keymap with nothing bound
+    pub fn empty() -> Keymap {
+        Keymap {
+            bindings: BTreeMap::new(),
+        }
+    }
+
+    /// the default keymap with the actions in `keys` rebound, each to a comma separated
+    /// list of chords such as `"ctrl+z, backspace"`, or to nothing with an empty string.
+    /// entries that can't be used are skipped and returned as errors
+    pub fn from_config(keys: &BTreeMap<String, String>) -> (Keymap, Vec<KeymapError>) {
+        let mut keymap = Keymap::default();
+        let mut errors = Vec::new();
+        let mut rebound = Vec::new();
+        for (name, chords) in keys {
+            let action = match Action::by_name(name) {
+                Some(action) => action,
+                None => {
+                    errors.push(KeymapError::UnknownAction(name.clone()));
+                    continue;
+                }
+            };
+            let chords: Result<Vec<KeyChord>, _> = chords
+                .split(',')
+                .filter(|chord| !chord.trim().is_empty())
+                .map(str::parse)
+                .collect();
+            match chords {
+                Ok(chords) => rebound.push((action, chords)),
+                Err(source) => errors.push(KeymapError::Chord { action, source }),
+            }
+        }
+
+        // the configured chords replace the defaults they clash with, but not each other
+        for (action, _) in &rebound {
+            keymap.bindings.remove(action);
+        }
+        let defaults = keymap.clone();
+        for (action, chords) in rebound {
+            for chord in chords {
+                match keymap.action(chord) {
+                    Some(bound) if defaults.action(chord) == Some(bound) => {
+                        keymap.bind(chord, action);
+                        errors.push(KeymapError::Conflict {
+                            chord,
+                            kept: action,
+                            dropped: bound,
+                        });
+                    }
+                    Some(bound) if bound != action => errors.push(KeymapError::Conflict {
+                        chord,
+                        kept: bound,
+                        dropped: action,
+                    }),
+                    Some(_) => {}
+                    None => {
+                        keymap.bind(chord, action);
+                    }
+                }
+            }
+        }
+        (keymap, errors)
+    }
+
+    /// the action `chord` triggers
+    pub fn action(&self, chord: KeyChord) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(_, chords)| chords.contains(&chord))
+            .map(|(&action, _)| action)
+    }
+
+    /// the chords that trigger `action`
+    pub fn chords(&self, action: Action) -> &[KeyChord] {
+        self.bindings.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// binds `chord` to `action`, returns the action it was bound to before if any
+    pub fn bind(&mut self, chord: KeyChord, action: Action) -> Option<Action> {
+        let previous = self.unbind(chord);
+        self.bindings.entry(action).or_default().push(chord);
+        previous
+    }
+
+    /// returns the action `chord` was bound to
+    pub fn unbind(&mut self, chord: KeyChord) -> Option<Action> {
+        let action = self.action(chord)?;
+        if let Some(chords) = self.bindings.get_mut(&action) {
+            chords.retain(|&c| c != chord);
+        }
+        Some(action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chord(chord: &str) -> KeyChord {
+        chord.parse().unwrap()
+    }
+
+    fn from_config(keys: &[(&str, &str)]) -> (Keymap, Vec<KeymapError>) {
+        let keys = keys
+            .iter()
+            .map(|&(action, chords)| (action.to_owned(), chords.to_owned()))
+            .collect();
+        Keymap::from_config(&keys)
+    }
+
+    #[test]
+    fn chords() {
+        assert_eq!(
+            chord("Ctrl + Shift+z"),
+            KeyChord::new(
+                VirtualKeyCode::Z,
+                ModifiersState::CTRL | ModifiersState::SHIFT
+            )
+        );
+        assert_eq!(chord("shift+control+z").to_string(), "ctrl+shift+z");
+        assert_eq!(chord("esc").to_string(), "escape");
+        assert_eq!(
+            "ctrl+".parse::<KeyChord>(),
+            Err(ChordError::UnknownKey(String::new()))
+        );
+        assert_eq!(
+            "ctrl+shift".parse::<KeyChord>(),
+            Err(ChordError::MissingKey("ctrl+shift".into()))
+        );
+        assert_eq!(
+            "hyper+f".parse::<KeyChord>(),
+            Err(ChordError::UnknownKey("hyper".into()))
+        );
+        assert_eq!(
+            "a+b".parse::<KeyChord>(),
+            Err(ChordError::TooManyKeys("a+b".into()))
+        );
+    }
+
+    #[test]
+    fn defaults_have_no_conflicts() {
+        let (keymap, errors) = from_config(&[]);
+        assert_eq!(keymap, Keymap::default());
+        assert!(errors.is_empty());
+        let mut seen = Vec::new();
+        for &action in &Action::ALL {
+            for &chord in keymap.chords(action) {
+                assert!(!seen.contains(&chord), "{} is bound twice", chord);
+                seen.push(chord);
+            }
+        }
+    }
+
+    #[test]
+    fn rebinding() {
+        let (keymap, errors) = from_config(&[("undo", "backspace, ctrl+z"), ("quit", "")]);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(keymap.action(chord("backspace")), Some(Action::Undo));
+        assert_eq!(keymap.action(chord("ctrl+z")), Some(Action::Undo));
+        assert!(keymap.chords(Action::Quit).is_empty());
+        assert_eq!(keymap.action(chord("escape")), None);
+    }
+
+    #[test]
+    fn configured_chord_overrides_default() {
+        let (keymap, errors) = from_config(&[("flip", "t")]);
+        assert_eq!(keymap.action(chord("t")), Some(Action::FlipBoard));
+        // the configured chords replace the action's own defaults
+        assert_eq!(keymap.action(chord("f")), None);
+        assert!(keymap.chords(Action::NextTheme).is_empty());
+        assert_eq!(
+            errors,
+            vec![KeymapError::Conflict {
+                chord: chord("t"),
+                kept: Action::FlipBoard,
+                dropped: Action::NextTheme,
+            }]
+        );
+    }
+
+    #[test]
+    fn first_configured_chord_wins() {
+        // the config is read in the order of the action names
+        let (keymap, errors) = from_config(&[("undo", "x"), ("flip", "x, x")]);
+        assert_eq!(keymap.action(chord("x")), Some(Action::FlipBoard));
+        assert!(keymap.chords(Action::Undo).is_empty());
+        assert_eq!(
+            errors,
+            vec![KeymapError::Conflict {
+                chord: chord("x"),
+                kept: Action::FlipBoard,
+                dropped: Action::Undo,
+            }]
+        );
+
+        // a default taken by one configured action isn't given to another
+        let (keymap, errors) = from_config(&[("flip", "t"), ("undo", "t")]);
+        assert_eq!(keymap.action(chord("t")), Some(Action::FlipBoard));
+        assert_eq!(
+            errors,
+            vec![
+                KeymapError::Conflict {
+                    chord: chord("t"),
+                    kept: Action::FlipBoard,
+                    dropped: Action::NextTheme,
+                },
+                KeymapError::Conflict {
+                    chord: chord("t"),
+                    kept: Action::FlipBoard,
+                    dropped: Action::Undo,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn unusable_entries_are_skipped() {
+        let (keymap, errors) =
+            from_config(&[("jump", "j"), ("flip", "ctrl+shift"), ("Undo", "backspace")]);
+        assert_eq!(
+            errors,
+            vec![
+                KeymapError::Chord {
+                    action: Action::FlipBoard,
+                    source: ChordError::MissingKey("ctrl+shift".into()),
+                },
+                KeymapError::UnknownAction("jump".into()),
+            ]
+        );
+        // a bad entry leaves the action's defaults alone
+        assert_eq!(keymap.action(chord("f")), Some(Action::FlipBoard));
+        assert_eq!(keymap.action(chord("j")), None);
+        assert_eq!(keymap.action(chord("backspace")), Some(Action::Undo));
+    }
+}
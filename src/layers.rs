@@ -0,0 +1,242 @@
+use std::any::Any;
+
+use crate::renderable::Renderable;
+
+/// identifies a layer of a `BoardView`, the builtin layers have fixed ids and can be
+/// reordered or hidden like any other
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LayerId(usize);
+
+impl LayerId {
+    pub const BACKGROUND: LayerId = LayerId(0);
+    pub const MARKERS: LayerId = LayerId(1);
+    pub const PIECES: LayerId = LayerId(2);
+    pub const ANNOTATIONS: LayerId = LayerId(3);
+    pub const DRAGGED_PIECE: LayerId = LayerId(4);
+    pub const OVERLAY: LayerId = LayerId(5);
+    pub const OVERLAY_PIECES: LayerId = LayerId(6);
+    /// the files and ranks along the edges, `set_coordinates` shows or hides them
+    pub const COORDINATES: LayerId = LayerId(7);
+
+    /// the builtin layers from bottom to top in their default order
+    pub const BUILTIN: [LayerId; 8] = [
+        LayerId::BACKGROUND,
+        LayerId::COORDINATES,
+        LayerId::MARKERS,
+        LayerId::PIECES,
+        LayerId::ANNOTATIONS,
+        LayerId::DRAGGED_PIECE,
+        LayerId::OVERLAY,
+        LayerId::OVERLAY_PIECES,
+    ];
+
+    pub fn is_builtin(&self) -> bool {
+        LayerId::BUILTIN.contains(self)
+    }
+}
+
+/// lets custom layers be handed back as their concrete type
+pub trait CustomLayer: Renderable {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Renderable + 'static> CustomLayer for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+pub struct Layer {
+    pub id: LayerId,
+    pub visible: bool,
+    /// `None` for the builtin layers, which `BoardView` owns directly
+    pub custom: Option<Box<dyn CustomLayer>>,
+}
+
+/// the order layers are drawn in, from bottom to top
+pub struct LayerStack {
+    layers: Vec<Layer>,
+    next_id: usize,
+}
+
+impl Default for LayerStack {
+    fn default() -> LayerStack {
+        LayerStack {
+            layers: LayerId::BUILTIN
+                .iter()
+                .map(|&id| Layer {
+                    id,
+                    visible: true,
+                    custom: None,
+                })
+                .collect(),
+            next_id: LayerId::BUILTIN.len(),
+        }
+    }
+}
+
+impl LayerStack {
+    pub fn ids(&self) -> Vec<LayerId> {
+        self.layers.iter().map(|layer| layer.id).collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Layer> {
+        self.layers.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Layer> {
+        self.layers.iter_mut()
+    }
+
+    fn position(&self, id: LayerId) -> Option<usize> {
+        self.layers.iter().position(|layer| layer.id == id)
+    }
+
+    fn get_mut(&mut self, id: LayerId) -> Option<&mut Layer> {
+        self.layers.iter_mut().find(|layer| layer.id == id)
+    }
+
+    /// inserts `layer` at `index`, clamped to the top of the stack
+    pub fn insert(&mut self, index: usize, layer: Box<dyn CustomLayer>) -> LayerId {
+        let id = LayerId(self.next_id);
+        self.next_id += 1;
+        let index = index.min(self.layers.len());
+        self.layers.insert(
+            index,
+            Layer {
+                id,
+                visible: true,
+                custom: Some(layer),
+            },
+        );
+        id
+    }
+
+    /// removes a custom layer, returns false for builtin layers, which can only be hidden
+    pub fn remove(&mut self, id: LayerId) -> bool {
+        match self.position(id) {
+            Some(index) if !id.is_builtin() => {
+                self.layers.remove(index);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// moves the layer to `index`, clamped to the top of the stack, returns false if there's
+    /// no such layer
+    pub fn move_to(&mut self, id: LayerId, index: usize) -> bool {
+        match self.position(id) {
+            Some(from) => {
+                let layer = self.layers.remove(from);
+                let index = index.min(self.layers.len());
+                self.layers.insert(index, layer);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_visible(&self, id: LayerId) -> bool {
+        matches!(self.position(id), Some(index) if self.layers[index].visible)
+    }
+
+    /// returns false if there's no such layer
+    pub fn set_visible(&mut self, id: LayerId, visible: bool) -> bool {
+        match self.get_mut(id) {
+            Some(layer) => {
+                layer.visible = visible;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn custom(&self, id: LayerId) -> Option<&(dyn CustomLayer + 'static)> {
+        self.layers
+            .iter()
+            .find(|layer| layer.id == id)?
+            .custom
+            .as_deref()
+    }
+
+    pub fn custom_mut(&mut self, id: LayerId) -> Option<&mut (dyn CustomLayer + 'static)> {
+        self.get_mut(id)?.custom.as_deref_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{Board, Orientation};
+
+    struct Marker(u32);
+
+    impl Renderable for Marker {
+        fn prepare(&mut self, _queue: &wgpu::Queue, _board: &Board, _orientation: Orientation) {}
+
+        fn render<'a>(&'a self, _render_pass: &mut wgpu::RenderPass<'a>) {}
+    }
+
+    #[test]
+    fn builtin_order_and_visibility() {
+        let stack = LayerStack::default();
+        assert_eq!(stack.ids(), LayerId::BUILTIN.to_vec());
+        for &id in &LayerId::BUILTIN {
+            assert!(id.is_builtin());
+            assert!(stack.is_visible(id));
+        }
+    }
+
+    #[test]
+    fn hiding_and_showing() {
+        let mut stack = LayerStack::default();
+        assert!(stack.set_visible(LayerId::PIECES, false));
+        assert!(!stack.is_visible(LayerId::PIECES));
+        assert!(stack.set_visible(LayerId::COORDINATES, false));
+        assert!(!stack.is_visible(LayerId::COORDINATES));
+        assert!(stack.set_visible(LayerId::PIECES, true));
+        assert!(stack.is_visible(LayerId::PIECES));
+
+        let missing = LayerId(100);
+        assert!(!stack.set_visible(missing, true));
+        assert!(!stack.is_visible(missing));
+    }
+
+    #[test]
+    fn custom_layers() {
+        let mut stack = LayerStack::default();
+        let below_pieces = stack.insert(3, Box::new(Marker(1)));
+        let top = stack.insert(usize::MAX, Box::new(Marker(2)));
+        assert!(!below_pieces.is_builtin());
+        assert_ne!(below_pieces, top);
+
+        let ids = stack.ids();
+        assert_eq!(ids.len(), LayerId::BUILTIN.len() + 2);
+        assert_eq!(ids[2], LayerId::MARKERS);
+        assert_eq!(ids[3], below_pieces);
+        assert_eq!(ids[4], LayerId::PIECES);
+        assert_eq!(ids.last(), Some(&top));
+        assert!(stack.is_visible(below_pieces));
+
+        let marker = stack.custom(top).unwrap().as_any().downcast_ref::<Marker>();
+        assert_eq!(marker.map(|marker| marker.0), Some(2));
+        assert!(stack.custom(LayerId::PIECES).is_none());
+
+        assert!(stack.move_to(top, 0));
+        assert_eq!(stack.ids()[0], top);
+        assert!(stack.move_to(LayerId::BACKGROUND, usize::MAX));
+        assert_eq!(stack.ids().last(), Some(&LayerId::BACKGROUND));
+
+        assert!(!stack.remove(LayerId::PIECES));
+        assert!(stack.remove(below_pieces));
+        assert!(!stack.remove(below_pieces));
+        assert!(!stack.ids().contains(&below_pieces));
+        assert!(!stack.move_to(below_pieces, 0));
+    }
+}
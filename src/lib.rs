@@ -1,47 +1,146 @@
-use std::iter;
+use std::{
+    iter,
+    ops::{Deref, DerefMut},
+    sync::Arc,
+};
 
-use renderable::Renderable;
 use winit::{dpi::PhysicalSize, window::Window};
 
-mod piece;
+mod animation;
 
-mod renderable;
+mod atlas;
+
+pub mod renderable;
+use renderable::Viewport;
+
+mod layers;
+pub use layers::LayerId;
+
+mod renderer;
+use renderer::RenderTarget;
+pub use renderer::{BoardRenderer, PieceSetRenderer, Renderer};
+
+pub mod software;
+
+pub mod svg;
+
+pub mod text;
+
+pub mod export;
+
+mod blit;
+
+mod readback;
+
+pub mod error;
+use error::Error;
 
 mod quad;
 
 mod background;
-use background::Background;
+
+mod coordinates;
 
 mod pieces;
-use pieces::PiecesView;
+
+mod markers;
+
+pub mod annotation;
+
+mod annotations;
+
+pub mod promotion;
+
+mod overlay;
 
 pub mod board;
 use board::Board;
 
+pub mod piece;
+
+pub mod pgn;
+pub mod rules;
+
+pub mod selection;
+
+pub mod piece_set;
+
+pub mod theme;
+
+pub mod config;
+
+pub mod keymap;
+
+/// the msaa sample count used by `BoardView::create`
+pub const DEFAULT_SAMPLE_COUNT: u32 = 4;
+
+/// how `BoardView::create_with_options` picks its adapter and draws
+#[derive(Debug, Clone, Copy)]
+pub struct BoardViewOptions {
+    /// samples per pixel, either 1 which disables msaa or 4
+    pub sample_count: u32,
+    pub power_preference: wgpu::PowerPreference,
+    /// the backends to look for an adapter on first
+    pub backends: wgpu::BackendBit,
+    /// looked through when `backends` has no compatible adapter, for instance the
+    /// `SECONDARY` backends which include gl and software implementations
+    pub fallback_backends: Option<wgpu::BackendBit>,
+}
+
+impl Default for BoardViewOptions {
+    fn default() -> BoardViewOptions {
+        BoardViewOptions {
+            sample_count: DEFAULT_SAMPLE_COUNT,
+            power_preference: wgpu::PowerPreference::default(),
+            backends: wgpu::BackendBit::PRIMARY,
+            fallback_backends: Some(wgpu::BackendBit::SECONDARY),
+        }
+    }
+}
+
+/// a `BoardRenderer` drawing to a window it owns the surface of, everything but rendering
+/// and resizing is done through the renderer it derefs to
 pub struct BoardView {
     surface: wgpu::Surface,
-    device: wgpu::Device,
-    queue: wgpu::Queue,
     sc_desc: wgpu::SwapChainDescriptor,
     swap_chain: wgpu::SwapChain,
+    /// the texture layers draw into when multisampling, resolved into the frame
+    multisampled_framebuffer: Option<wgpu::TextureView>,
     size: PhysicalSize<u32>,
-    background: Background,
-    pieces_view: PiecesView, 
+    renderer: BoardRenderer,
 }
 
 impl BoardView {
-    pub async fn create(window: &Window) -> BoardView {
+    pub async fn create(window: &Window) -> Result<BoardView, Error> {
+        BoardView::create_with_options(window, &BoardViewOptions::default()).await
+    }
+
+    pub async fn create_with_options(
+        window: &Window,
+        options: &BoardViewOptions,
+    ) -> Result<BoardView, Error> {
         let size = window.inner_size();
 
-        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
-        let surface = unsafe { instance.create_surface(window) };
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
-            })
-            .await
-            .unwrap();
+        let mut backends = vec![options.backends];
+        backends.extend(options.fallback_backends);
+        let mut found = None;
+        for backends in backends {
+            let instance = wgpu::Instance::new(backends);
+            let surface = unsafe { instance.create_surface(window) };
+            let adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: options.power_preference,
+                    compatible_surface: Some(&surface),
+                })
+                .await;
+            if let Some(adapter) = adapter {
+                found = Some((surface, adapter));
+                break;
+            }
+            log::warn!("no compatible adapter on {:?}", backends);
+        }
+        let (surface, adapter) = found.ok_or(Error::NoAdapter)?;
+        log::info!("using {:?}", adapter.get_info());
 
         let (device, queue) = adapter
             .request_device(
@@ -52,8 +151,7 @@ impl BoardView {
                 },
                 None,
             )
-            .await
-            .unwrap();
+            .await?;
 
         let sc_desc = wgpu::SwapChainDescriptor {
             usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
@@ -64,72 +162,136 @@ impl BoardView {
         };
         let swap_chain = device.create_swap_chain(&surface, &sc_desc);
 
-        let background = Background::new(&device, &sc_desc);
+        let mut renderer = BoardRenderer::new(
+            Arc::new(device),
+            Arc::new(queue),
+            sc_desc.format,
+            options.sample_count,
+        )?;
+        renderer.set_viewport(window_viewport(size));
 
-        let pieces_view = PiecesView::new(&device, &queue, &sc_desc);
+        let multisampled_framebuffer = create_multisampled_framebuffer(
+            renderer.device(),
+            &sc_desc,
+            renderer.sample_count(),
+        );
 
-        Self {
+        Ok(Self {
             surface,
-            device,
-            queue,
             sc_desc,
             swap_chain,
+            multisampled_framebuffer,
             size,
-            background,
-            pieces_view,
-        }
+            renderer,
+        })
     }
 
     pub fn size(&self) -> PhysicalSize<u32> {
         return self.size;
     }
 
-    /// resizes the wgpu context to the given size, converted to a square, returns the new dimensions
+    /// resizes the wgpu context to the given size, the board is drawn in the largest square
+    /// centered in it. returns the square size the window should have for the board to fill it
     pub fn resize(&mut self, new_size: PhysicalSize<u32>) -> PhysicalSize<u32> {
         let square_size = new_size.width.max(new_size.height).max(1);
         log::info!("resizing to {:?} with square size {}", new_size, square_size);
         self.size = new_size;
-        self.sc_desc.width = square_size;
-        self.sc_desc.height = square_size;
-        self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+        self.sc_desc.width = new_size.width.max(1);
+        self.sc_desc.height = new_size.height.max(1);
+        let device = self.renderer.device();
+        self.swap_chain = device.create_swap_chain(&self.surface, &self.sc_desc);
+        self.multisampled_framebuffer =
+            create_multisampled_framebuffer(device, &self.sc_desc, self.renderer.sample_count());
+        self.renderer.set_viewport(window_viewport(new_size));
         PhysicalSize::new(square_size, square_size)
     }
 
     pub fn render(&mut self, board: &Board) -> Result<(), wgpu::SwapChainError> {
+        self.renderer.prepare(board);
+
         let frame = self.swap_chain.get_current_frame()?.output;
+        let target = match &self.multisampled_framebuffer {
+            Some(framebuffer) => RenderTarget {
+                view: framebuffer,
+                resolve_target: Some(&frame.view),
+            },
+            None => RenderTarget {
+                view: &frame.view,
+                resolve_target: None,
+            },
+        };
 
         let mut encoder = self
-            .device
+            .renderer
+            .device()
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: Some("Render Encoder"),
             });
 
-        {
-            let _ = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                    attachment: &frame.view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.2,
-                            b: 0.3,
-                            a: 1.0,
-                        }),
-                        store: true,
-                    },
-                }],
-                depth_stencil_attachment: None,
-            });
-        }
+        let clear = wgpu::LoadOp::Clear(wgpu::Color {
+            r: 0.1,
+            g: 0.2,
+            b: 0.3,
+            a: 1.0,
+        });
+        // the viewport `resize` set, so the board is drawn where `square_at` looks for it
+        let viewport = self.renderer.viewport();
+        self.renderer.draw(&mut encoder, &target, clear, Some(viewport));
 
-        self.background.render(&mut encoder, &mut self.queue, &frame, &board);
+        self.renderer.queue().submit(iter::once(encoder.finish()));
 
-        self.pieces_view.render(&mut encoder, &mut self.queue, &frame, &board);
+        Ok(())
+    }
+}
 
-        self.queue.submit(iter::once(encoder.finish()));
+impl Deref for BoardView {
+    type Target = BoardRenderer;
 
-        Ok(())
+    fn deref(&self) -> &BoardRenderer {
+        &self.renderer
+    }
+}
+
+impl DerefMut for BoardView {
+    fn deref_mut(&mut self) -> &mut BoardRenderer {
+        &mut self.renderer
+    }
+}
+
+/// the board is the largest square centered in the window
+fn window_viewport(size: PhysicalSize<u32>) -> Viewport {
+    let width = size.width as f32;
+    let height = size.height as f32;
+    let side = width.min(height).max(1.0);
+    Viewport {
+        x: (width - side) / 2.0,
+        y: (height - side) / 2.0,
+        width: side,
+        height: side,
+    }
+}
+
+/// the multisampled color target matching the swap chain, or `None` without msaa
+fn create_multisampled_framebuffer(
+    device: &wgpu::Device,
+    sc_desc: &wgpu::SwapChainDescriptor,
+    sample_count: u32,
+) -> Option<wgpu::TextureView> {
+    if sample_count <= 1 {
+        return None;
     }
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        size: wgpu::Extent3d {
+            width: sc_desc.width,
+            height: sc_desc.height,
+            depth: 1,
+        },
+        mip_level_count: 1,
+        sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format: sc_desc.format,
+        usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
+        label: Some("Multisampled Framebuffer"),
+    });
+    Some(texture.create_view(&wgpu::TextureViewDescriptor::default()))
 }
@@ -1,17 +1,564 @@
-use image::GenericImageView;
+use std::{
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, bail, Context};
+
+use image::{GenericImageView, RgbaImage};
 use winit::{
-    dpi::PhysicalSize,
-    event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent},
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{
+        ElementState, Event, KeyboardInput, ModifiersState, MouseButton, StartCause,
+        VirtualKeyCode, WindowEvent,
+    },
     event_loop::{ControlFlow, EventLoop},
     window::{Icon, WindowBuilder},
 };
 
-use chessboard_rs::{board, BoardView};
+use chessboard_rs::{
+    annotation::{self, Annotation},
+    board::Orientation,
+    config::Config,
+    keymap::{Action, KeyChord, Keymap},
+    pgn,
+    piece::Type,
+    piece_set::{PieceSet, SvgPieceSet},
+    promotion::PromotionChooser,
+    rules::{Move, Position},
+    selection::{Selection, SelectionEvent},
+    software::SoftwareRenderer,
+    svg::SvgRenderer,
+    theme::{Highlight, Theme},
+    BoardView, PieceSetRenderer, Renderer,
+};
+
+const HELP: &str = "\
+Shows a chess board to play on
+
+USAGE:
+    chessboard-rs [OPTIONS]
+
+OPTIONS:
+    --fen <FEN>        starts from this position instead of the usual one
+    --pgn <FILE>       shows a game of a pgn file
+    --game <N>         which game of the pgn file, counted from 1 [default: 1]
+    --ply <N>          how many moves of the game are played [default: all of them]
+    --flip             views the board from black's side
+    --theme <NAME>     brown, green, blue or high-contrast
+    --pieces <PATH>    a directory with images named like wK.svg or wK.png, or an atlas .toml
+    --size <WxH>       the size of the window, or of the image with --headless. the board is
+                       square, so both take the larger side [default: 600x600]
+    --config <FILE>    where the settings are kept [default: chessboard-rs/config.toml in the
+                       config directory]
+    --headless         draws the board into --output instead of opening a window
+    --output <FILE>    the .png or .svg file --headless draws to
+    -h, --help         prints this help
+
+SETTINGS:
+    The config file keeps the window size and position, theme, pieces, orientation,
+    animation_speed, coordinates and key bindings between runs. screenshot_dir sets where
+    the screenshot key (F12) saves pngs [default: the working directory]
+";
+
+/// the options given on the command line
+struct Args {
+    fen: Option<String>,
+    pgn: Option<PathBuf>,
+    game: usize,
+    ply: Option<usize>,
+    flip: bool,
+    theme: Option<String>,
+    pieces: Option<PathBuf>,
+    size: Option<(u32, u32)>,
+    config: Option<PathBuf>,
+    headless: bool,
+    output: Option<PathBuf>,
+}
+
+/// returns `None` when only the help was asked for
+fn parse_args() -> anyhow::Result<Option<Args>> {
+    let mut args = pico_args::Arguments::from_env();
+    if args.contains(["-h", "--help"]) {
+        return Ok(None);
+    }
+    let parsed = Args {
+        fen: args.opt_value_from_str("--fen")?,
+        pgn: args.opt_value_from_str("--pgn")?,
+        game: args.opt_value_from_str("--game")?.unwrap_or(1),
+        ply: args.opt_value_from_str("--ply")?,
+        flip: args.contains("--flip"),
+        theme: args.opt_value_from_str("--theme")?,
+        pieces: args.opt_value_from_str("--pieces")?,
+        size: args.opt_value_from_fn("--size", parse_size)?,
+        config: args.opt_value_from_str("--config")?,
+        headless: args.contains("--headless"),
+        output: args.opt_value_from_str("--output")?,
+    };
+    let unused = args.finish();
+    if !unused.is_empty() {
+        bail!("unexpected arguments {:?}, see --help", unused);
+    }
+
+    if parsed.fen.is_some() && parsed.pgn.is_some() {
+        bail!("--fen and --pgn can't be used together");
+    }
+    if parsed.pgn.is_none() && (parsed.ply.is_some() || parsed.game != 1) {
+        bail!("--game and --ply need a --pgn file");
+    }
+    if parsed.game == 0 {
+        bail!("games are counted from 1");
+    }
+    if let Some(theme) = &parsed.theme {
+        if Theme::by_name(theme).is_none() {
+            let names: Vec<_> = Theme::PRESETS.iter().map(|(name, _)| *name).collect();
+            bail!("unknown theme {}, try one of {}", theme, names.join(", "));
+        }
+    }
+    if parsed.headless != parsed.output.is_some() {
+        bail!("--headless and --output need each other");
+    }
+    Ok(Some(parsed))
+}
+
+/// parses sizes like `800x600`
+fn parse_size(size: &str) -> anyhow::Result<(u32, u32)> {
+    let split = size
+        .find('x')
+        .ok_or_else(|| anyhow!("expected a size like 800x600"))?;
+    let size = (size[..split].parse()?, size[split + 1..].parse()?);
+    if size.0 == 0 || size.1 == 0 {
+        bail!("the size can't be empty");
+    }
+    Ok(size)
+}
+
+/// the moves of the game from `start`, of which the first `ply` are on the board
+struct History {
+    start: Position,
+    moves: Vec<Move>,
+    /// the comment after each ply, starting with the one before the first move
+    comments: Vec<String>,
+    ply: usize,
+    /// the position after `ply` moves
+    position: Position,
+    /// the moves taken back by each undo along with their comments, the last is played
+    /// again first
+    undone: Vec<(Vec<Move>, Vec<String>)>,
+}
+
+impl History {
+    fn new(start: Position, moves: Vec<Move>, comments: Vec<String>, ply: usize) -> History {
+        let mut history = History {
+            start,
+            moves,
+            comments,
+            ply: 0,
+            position: start,
+            undone: Vec::new(),
+        };
+        history.go_to(ply);
+        history
+    }
+
+    /// plays `mv` after the current ply, replacing the moves that came after it
+    fn play(&mut self, mv: Move) -> bool {
+        if !self.position.play(mv) {
+            return false;
+        }
+        self.moves.truncate(self.ply);
+        self.comments.truncate(self.ply + 1);
+        self.moves.push(mv);
+        self.comments.push(String::new());
+        self.ply += 1;
+        self.undone.clear();
+        true
+    }
+
+    /// shows the position after `ply` moves, returns false if it's already shown or there
+    /// aren't that many moves
+    fn go_to(&mut self, ply: usize) -> bool {
+        if ply == self.ply || ply > self.moves.len() {
+            return false;
+        }
+        self.position = self.start;
+        for &mv in &self.moves[..ply] {
+            self.position.play(mv);
+        }
+        self.ply = ply;
+        true
+    }
+
+    /// takes back the move that led to the shown position and shows the one before it. the
+    /// moves after it can't be played without it, so they're taken back along with it
+    fn undo(&mut self) -> bool {
+        if self.ply == 0 {
+            return false;
+        }
+        let ply = self.ply - 1;
+        let moves = self.moves.split_off(ply);
+        let comments = self.comments.split_off(ply + 1);
+        self.undone.push((moves, comments));
+        self.go_to(ply);
+        true
+    }
+
+    /// plays the moves taken back last again and shows the position after the first of them
+    fn redo(&mut self) -> bool {
+        let (moves, comments) = match self.undone.pop() {
+            Some(undone) => undone,
+            None => return false,
+        };
+        let ply = self.moves.len() + 1;
+        self.moves.extend(moves);
+        self.comments.extend(comments);
+        self.go_to(ply);
+        true
+    }
+
+    fn last_move(&self) -> Option<Move> {
+        self.ply.checked_sub(1).map(|i| self.moves[i])
+    }
+
+    /// the annotations drawn in the comment after the current ply
+    fn annotations(&self) -> Vec<Annotation> {
+        self.comments
+            .get(self.ply)
+            .map_or_else(Vec::new, |comment| annotation::parse_comment(comment))
+    }
+}
+
+fn load_history(args: &Args) -> anyhow::Result<History> {
+    if let Some(fen) = &args.fen {
+        let start = Position::from_fen(fen)?;
+        return Ok(History::new(start, Vec::new(), vec![String::new()], 0));
+    }
+    let path = match &args.pgn {
+        Some(path) => path,
+        None => {
+            let start = Position::default();
+            return Ok(History::new(start, Vec::new(), vec![String::new()], 0));
+        }
+    };
+
+    let text =
+        fs::read_to_string(path).with_context(|| format!("unable to read {}", path.display()))?;
+    let games = pgn::parse(&text).with_context(|| format!("unable to parse {}", path.display()))?;
+    let game = games.get(args.game - 1).ok_or_else(|| {
+        anyhow!(
+            "{} has {} games, not {}",
+            path.display(),
+            games.len(),
+            args.game
+        )
+    })?;
+    let ply = args.ply.unwrap_or(game.moves.len());
+    if ply > game.moves.len() {
+        bail!("the game only has {} plies", game.moves.len());
+    }
+    Ok(History::new(
+        game.start,
+        game.moves.clone(),
+        game.comments.clone(),
+        ply,
+    ))
+}
+
+enum Pieces {
+    Raster(PieceSet),
+    Svg(SvgPieceSet),
+}
+
+/// loads svgs from a directory that has them, otherwise pngs, or an atlas from a toml file
+fn load_pieces(path: &Path) -> anyhow::Result<Pieces> {
+    let pieces = if path.extension() == Some(OsStr::new("toml")) {
+        Pieces::Raster(PieceSet::load_atlas(path)?)
+    } else if path.join("wK.svg").exists() {
+        Pieces::Svg(SvgPieceSet::load_dir(path)?)
+    } else {
+        Pieces::Raster(PieceSet::load_dir(path)?)
+    };
+    Ok(pieces)
+}
+
+/// the config with the options given on the command line on top, which only last until
+/// the viewer exits
+fn apply_args(config: &Config, args: &Args) -> Config {
+    let mut settings = config.clone();
+    if let Some(theme) = &args.theme {
+        settings.theme = theme.clone();
+    }
+    if args.flip {
+        settings.orientation = Orientation::Black;
+    }
+    if let Some(pieces) = &args.pieces {
+        settings.pieces = Some(pieces.clone());
+    }
+    if let Some((width, height)) = args.size {
+        settings.window.width = width;
+        settings.window.height = height;
+    }
+    settings
+}
+
+/// writes `config` back if it changed since it was `saved`, a config that can't be written
+/// is only worth a warning
+fn save_config(config: &Config, saved: &mut Config, path: Option<&Path>) {
+    if config == saved {
+        return;
+    }
+    if let Some(path) = path {
+        match config.save(saved, path) {
+            Ok(()) => *saved = config.clone(),
+            Err(err) => log::warn!("unable to save the settings: {}", err),
+        }
+    }
+}
+
+/// applies the settings every renderer shares and shows the ply the history is at
+fn configure(renderer: &mut dyn Renderer, settings: &Config, history: &History) {
+    renderer.set_theme(settings.theme());
+    renderer.set_orientation(settings.orientation);
+    renderer.set_coordinates(settings.coordinates);
+    show_ply(history, renderer);
+}
+
+/// draws the board into `output` without a window, as an svg if it's named like one
+fn render_headless(
+    settings: &Config,
+    output: &Path,
+    history: &History,
+    pieces: Option<Pieces>,
+) -> anyhow::Result<()> {
+    // the larger side, as the window is squared by `BoardView::resize`
+    let size = settings.window.width.max(settings.window.height);
+    if output.extension() == Some(OsStr::new("svg")) {
+        let mut renderer = SvgRenderer::new()?;
+        configure(&mut renderer, settings, history);
+        match pieces {
+            Some(Pieces::Raster(set)) => renderer.set_piece_set(&set),
+            Some(Pieces::Svg(set)) => renderer.set_svg_piece_set(&set),
+            None => {}
+        }
+        fs::write(output, renderer.render(&history.position.board, size))?;
+    } else {
+        let mut renderer = SoftwareRenderer::new()?;
+        configure(&mut renderer, settings, history);
+        match pieces {
+            Some(Pieces::Raster(set)) => renderer.set_piece_set(&set),
+            Some(Pieces::Svg(set)) => renderer.set_piece_set(&set.rasterize(size / 8)),
+            None => {}
+        }
+        renderer
+            .render(&history.position.board, size)
+            .save(output)?;
+    }
+    Ok(())
+}
+
+/// highlights `mv` along with the king if it's left in check
+fn highlight_move(renderer: &mut dyn Renderer, mv: Option<Move>, position: &Position) {
+    let squares: Vec<_> = mv.iter().flat_map(|mv| vec![mv.from, mv.to]).collect();
+    renderer.set_highlight(Highlight::LastMove, &squares);
+    let checked_king: Vec<_> = position
+        .king(position.turn)
+        .filter(|_| position.is_check())
+        .into_iter()
+        .collect();
+    renderer.set_highlight(Highlight::Check, &checked_king);
+}
+
+/// highlights the last move and draws the annotations of the ply the history is at
+fn show_ply(history: &History, renderer: &mut dyn Renderer) {
+    highlight_move(renderer, history.last_move(), &history.position);
+    renderer.set_annotations(&history.annotations());
+}
+
+/// plays `mv` and highlights it along with the king if it's now in check
+fn play_move(mv: Move, history: &mut History, board_view: &mut BoardView) -> bool {
+    if !history.play(mv) {
+        return false;
+    }
+    show_ply(history, &mut **board_view);
+    true
+}
+
+/// drops whatever piece was selected or dragged and closes the promotion picker
+fn cancel_selection(
+    selection: &mut Selection,
+    promotion: &mut Option<PromotionChooser>,
+    history: &mut History,
+    board_view: &mut BoardView,
+) {
+    selection.clear();
+    board_view.clear_move_targets();
+    board_view.set_highlight(Highlight::Selected, &[]);
+    board_view.drop_piece();
+    finish_promotion(None, promotion, history, board_view);
+}
+
+/// updates the board after the selection changed, returns whether a move was played
+fn handle_selection(
+    event: SelectionEvent,
+    selection: &Selection,
+    history: &mut History,
+    promotion: &mut Option<PromotionChooser>,
+    board_view: &mut BoardView,
+) -> bool {
+    match event {
+        SelectionEvent::Selected(square) => {
+            let captures: Vec<_> = selection
+                .targets()
+                .iter()
+                .copied()
+                .filter(|&to| history.position.is_capture(Move::new(square, to)))
+                .collect();
+            board_view.set_move_targets(selection.targets(), &captures);
+            board_view.set_highlight(Highlight::Selected, &[square]);
+        }
+        SelectionEvent::Deselected => {
+            board_view.clear_move_targets();
+            board_view.set_highlight(Highlight::Selected, &[]);
+        }
+        SelectionEvent::Move { from, to } => {
+            board_view.clear_move_targets();
+            board_view.set_highlight(Highlight::Selected, &[]);
+            let mv = Move::new(from, to);
+            let promotes = Move {
+                promotion: Some(Type::Queen),
+                ..mv
+            };
+            if play_move(mv, history, board_view) {
+                return true;
+            } else if history.position.is_legal(promotes) {
+                *promotion = PromotionChooser::new(from, to, history.position.turn);
+                board_view.set_promotion_chooser(*promotion);
+            } else {
+                log::warn!("rejected illegal move {} to {}", from, to);
+            }
+        }
+        SelectionEvent::Ignored => {}
+    }
+    false
+}
+
+/// closes the promotion picker, promoting to `choice` or canceling the move if it's `None`
+fn finish_promotion(
+    choice: Option<Type>,
+    promotion: &mut Option<PromotionChooser>,
+    history: &mut History,
+    board_view: &mut BoardView,
+) {
+    if let (Some(chooser), Some(choice)) = (promotion.take(), choice) {
+        let mv = Move {
+            from: chooser.from,
+            to: chooser.to,
+            promotion: Some(choice),
+        };
+        play_move(mv, history, board_view);
+    }
+    board_view.set_promotion_chooser(None);
+}
+
+/// handles a key press while the promotion picker is open, returns `Some` once the picker
+/// should close with the chosen piece, or with `None` if it was canceled
+fn promotion_key(chooser: &mut PromotionChooser, key: VirtualKeyCode) -> Option<Option<Type>> {
+    match key {
+        VirtualKeyCode::Up | VirtualKeyCode::Left => chooser.highlight_previous(),
+        VirtualKeyCode::Down | VirtualKeyCode::Right => chooser.highlight_next(),
+        VirtualKeyCode::Return | VirtualKeyCode::Space => return Some(Some(chooser.highlighted())),
+        VirtualKeyCode::Q => return Some(Some(Type::Queen)),
+        VirtualKeyCode::N => return Some(Some(Type::Knight)),
+        VirtualKeyCode::R => return Some(Some(Type::Rook)),
+        VirtualKeyCode::B => return Some(Some(Type::Bishop)),
+        VirtualKeyCode::Escape => return Some(None),
+        _ => {}
+    }
+    None
+}
+
+/// the current utc time like `2021-04-30_18-05-09`, which sorts chronologically
+fn timestamp() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let (days, time) = (seconds / 86400, seconds % 86400);
+
+    // converts days since 1970-01-01 to a date, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = days as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+    format!(
+        "{:04}-{:02}-{:02}_{:02}-{:02}-{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+/// saves `image` as a png named after the current time in `dir`, returns where it went
+fn save_screenshot(image: &RgbaImage, dir: &Path) -> anyhow::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let name = format!("screenshot-{}", timestamp());
+    let mut path = dir.join(format!("{}.png", name));
+    let mut copy = 1;
+    while path.exists() {
+        copy += 1;
+        path = dir.join(format!("{}-{}.png", name, copy));
+    }
+    image.save(&path)?;
+    Ok(path)
+}
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> anyhow::Result<()> {
     env_logger::init();
 
+    let args = match parse_args()? {
+        Some(args) => args,
+        None => {
+            print!("{}", HELP);
+            return Ok(());
+        }
+    };
+    let config_path = args.config.clone().or_else(Config::default_path);
+    let mut config = config_path.as_deref().map(Config::load).unwrap_or_default();
+    let settings = apply_args(&config, &args);
+
+    let history = load_history(&args)?;
+    let pieces = match settings.pieces.as_deref().map(load_pieces).transpose() {
+        Ok(pieces) => pieces,
+        Err(err) if args.pieces.is_none() => {
+            log::warn!(
+                "unable to load the configured pieces, using the builtin ones: {:#}",
+                err
+            );
+            None
+        }
+        Err(err) => return Err(err),
+    };
+    if let Some(output) = &args.output {
+        return render_headless(&settings, output, &history, pieces);
+    }
+
     let icon_data = include_bytes!("images/icon.png");
     let icon_image = image::load_from_memory(icon_data)?;
     let icon_dims = icon_image.dimensions();
@@ -22,33 +569,214 @@ async fn main() -> anyhow::Result<()> {
     let window = WindowBuilder::new()
         .with_title("Chess Board")
         .with_window_icon(Some(icon))
-        .with_inner_size(PhysicalSize::new(600, 600))
+        .with_inner_size(PhysicalSize::new(
+            settings.window.width,
+            settings.window.height,
+        ))
         .with_resizable(false)
         .build(&event_loop)?;
+    if let Some((x, y)) = settings.window.position {
+        window.set_outer_position(PhysicalPosition::new(x, y));
+    }
 
-    let board = board::default_board();
-    let mut board_view = BoardView::create(&window).await;
+    let mut history = history;
+    let mut selection = Selection::default();
+    let mut promotion = None;
+    let mut board_view = BoardView::create(&window).await?;
+    configure(&mut *board_view, &settings, &history);
+    board_view.set_animation_duration(settings.animation_duration());
+    match pieces {
+        Some(Pieces::Raster(set)) => board_view.set_piece_set(&set),
+        Some(Pieces::Svg(set)) => board_view.set_svg_piece_set(set),
+        None => {}
+    }
+    let (keymap, errors) = Keymap::from_config(&settings.keys);
+    for err in errors {
+        log::warn!("{}", err);
+    }
+    let mut modifiers = ModifiersState::empty();
     let mut square_size = None;
+    // whether the last frame was drawn partway through a move sliding
+    let mut animating = false;
+    let mut cursor = PhysicalPosition::new(0.0, 0.0);
+    let mut theme_index = Theme::PRESETS
+        .iter()
+        .position(|(name, _)| name.eq_ignore_ascii_case(&settings.theme))
+        .unwrap_or(0);
+    let mut saved_config = config.clone();
 
     event_loop.run(move |event, _, control_flow| match event {
+        // only wake up for input, redraws are requested when something changes
+        Event::NewEvents(StartCause::Init) => *control_flow = ControlFlow::Wait,
         Event::WindowEvent {
             ref event,
             window_id,
         } if window_id == window.id() => match event {
             WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
-            WindowEvent::KeyboardInput { input, .. } => match input {
-                KeyboardInput {
-                    state: ElementState::Pressed,
-                    virtual_keycode: Some(VirtualKeyCode::Escape),
-                    ..
-                } => *control_flow = ControlFlow::Exit,
-                _ => {}
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                ..
+            } if promotion.is_some() => {
+                if let Some(chooser) = promotion.as_mut() {
+                    match promotion_key(chooser, *key) {
+                        Some(choice) => {
+                            finish_promotion(choice, &mut promotion, &mut history, &mut board_view)
+                        }
+                        None => board_view.set_promotion_chooser(promotion),
+                    }
+                }
+                window.request_redraw();
+            }
+            WindowEvent::ModifiersChanged(state) => modifiers = *state,
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                ..
+            } => match keymap.action(KeyChord::new(*key, modifiers)) {
+                Some(Action::Quit) => *control_flow = ControlFlow::Exit,
+                Some(Action::FlipBoard) => {
+                    let orientation = board_view.orientation().flipped();
+                    board_view.set_orientation(orientation);
+                    config.orientation = orientation;
+                    save_config(&config, &mut saved_config, config_path.as_deref());
+                    window.request_redraw();
+                }
+                Some(Action::ToggleCoordinates) => {
+                    let coordinates = !board_view.coordinates();
+                    board_view.set_coordinates(coordinates);
+                    config.coordinates = coordinates;
+                    save_config(&config, &mut saved_config, config_path.as_deref());
+                    window.request_redraw();
+                }
+                Some(Action::NextTheme) => {
+                    theme_index = (theme_index + 1) % Theme::PRESETS.len();
+                    let (name, theme) = Theme::PRESETS[theme_index];
+                    log::info!("switching to the {} theme", name);
+                    board_view.set_theme(theme);
+                    config.theme = name.to_owned();
+                    save_config(&config, &mut saved_config, config_path.as_deref());
+                    window.request_redraw();
+                }
+                Some(Action::Screenshot) => {
+                    let size = board_view.viewport().width as u32;
+                    let saved = board_view
+                        .render_to_image(&history.position.board, size)
+                        .map_err(anyhow::Error::from)
+                        .and_then(|image| save_screenshot(&image, &settings.screenshot_dir));
+                    match saved {
+                        Ok(path) => log::info!("saved a screenshot to {}", path.display()),
+                        Err(err) => log::error!("unable to take a screenshot: {}", err),
+                    }
+                }
+                Some(action) => {
+                    let changed = match action {
+                        Action::Undo => history.undo(),
+                        Action::Redo => history.redo(),
+                        Action::NextMove => history.go_to(history.ply + 1),
+                        Action::PreviousMove => history.ply > 0 && history.go_to(history.ply - 1),
+                        Action::FirstMove => history.go_to(0),
+                        Action::LastMove => history.go_to(history.moves.len()),
+                        _ => false,
+                    };
+                    if changed {
+                        cancel_selection(
+                            &mut selection,
+                            &mut promotion,
+                            &mut history,
+                            &mut board_view,
+                        );
+                        show_ply(&history, &mut *board_view);
+                        window.request_redraw();
+                    }
+                }
+                None => {}
             },
+            WindowEvent::CursorMoved {
+                position: cursor_position,
+                ..
+            } => {
+                cursor = *cursor_position;
+                if let Some(from) = selection.dragging() {
+                    board_view.drag_piece(from, cursor);
+                    window.request_redraw();
+                }
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } if promotion.is_some() => {
+                let choice = board_view
+                    .square_at(cursor)
+                    .and_then(|square| promotion.and_then(|p| p.choice_at(square)));
+                finish_promotion(choice, &mut promotion, &mut history, &mut board_view);
+                window.request_redraw();
+            }
+            WindowEvent::MouseInput {
+                button: MouseButton::Left,
+                ..
+            } if promotion.is_some() => {}
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                let square = board_view.square_at(cursor);
+                let event = match state {
+                    ElementState::Pressed => selection.press(square, &history.position),
+                    ElementState::Released => selection.release(square),
+                };
+                let played = handle_selection(
+                    event,
+                    &selection,
+                    &mut history,
+                    &mut promotion,
+                    &mut board_view,
+                );
+                match selection.dragging() {
+                    Some(from) => board_view.drag_piece(from, cursor),
+                    None => board_view.drop_piece(),
+                }
+                if played && *state == ElementState::Released {
+                    if let SelectionEvent::Move { to, .. } = event {
+                        // the piece was dropped on its destination so it shouldn't slide there
+                        board_view.skip_animation_for(to);
+                    }
+                }
+                window.request_redraw();
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Right,
+                ..
+            } => {
+                cancel_selection(
+                    &mut selection,
+                    &mut promotion,
+                    &mut history,
+                    &mut board_view,
+                );
+                window.request_redraw();
+            }
+            WindowEvent::Moved(outer_position) => {
+                config.window.position = Some((outer_position.x, outer_position.y));
+            }
             WindowEvent::Resized(physical_size) => {
                 square_size = Some(board_view.resize(*physical_size));
+                window.request_redraw();
             }
             WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
                 square_size = Some(board_view.resize(**new_inner_size));
+                window.request_redraw();
             }
             _ => {}
         },
@@ -57,7 +785,7 @@ async fn main() -> anyhow::Result<()> {
                 window.set_inner_size(square_inner);
                 square_size = None;
             }
-            match board_view.render(&board) {
+            match board_view.render(&history.position.board) {
                 Ok(_) => {}
                 Err(wgpu::SwapChainError::Lost) => {
                     board_view.resize(board_view.size());
@@ -65,6 +793,22 @@ async fn main() -> anyhow::Result<()> {
                 Err(wgpu::SwapChainError::OutOfMemory) => *control_flow = ControlFlow::Exit,
                 Err(e) => eprintln!("{:?}", e),
             }
+            // moves only start sliding while rendering, so the loop would otherwise sleep
+            // through the rest of the animation. the frame after it ends puts the pieces down
+            if board_view.is_animating() || animating {
+                window.request_redraw();
+            }
+            animating = board_view.is_animating();
+        }
+        // the window is only written back on the way out so moving it doesn't write the
+        // config over and over
+        Event::LoopDestroyed => {
+            if args.size.is_none() {
+                let size = window.inner_size();
+                config.window.width = size.width;
+                config.window.height = size.height;
+            }
+            save_config(&config, &mut saved_config, config_path.as_deref());
         }
         _ => {}
     });
@@ -0,0 +1,223 @@
+use std::ops::Range;
+
+use bytemuck::{Pod, Zeroable};
+use nalgebra::{Matrix4, Vector3};
+use wgpu::util::DeviceExt;
+
+use crate::{
+    board::{Board, Orientation, Square},
+    piece::board_coord_to_world,
+    quad::{INDICES, LAYOUT, VERTICES},
+    renderable::Renderable,
+};
+
+type MarkersBuffer = [MarkerRaw; 64];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarkerKind {
+    /// a quiet move onto an empty square
+    Dot,
+    /// a capture, including en passant onto the empty square behind the pawn
+    Ring,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct MarkerRaw {
+    position: Matrix4<f32>,
+    kind: u32,
+}
+
+impl MarkerRaw {
+    pub fn new(square: Square, kind: MarkerKind) -> MarkerRaw {
+        MarkerRaw {
+            position: Matrix4::new_scaling(1.0 / 8.0).append_translation(&Vector3::new(
+                board_coord_to_world(square.file as f32),
+                board_coord_to_world(square.rank as f32),
+                0.0,
+            )),
+            kind: kind as u32,
+        }
+    }
+
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        use std::mem;
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<MarkerRaw>() as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Float4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 12]>() as wgpu::BufferAddress,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Float4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<Matrix4<f32>>() as wgpu::BufferAddress,
+                    shader_location: 6,
+                    format: wgpu::VertexFormat::Uint,
+                },
+            ],
+        }
+    }
+}
+
+/// draws legal-move destination markers, dots on empty squares and rings on captures
+pub struct MarkersView {
+    pipeline: wgpu::RenderPipeline,
+    vert_buffer: wgpu::Buffer,
+    idx_buffer: wgpu::Buffer,
+    marker_buffer: wgpu::Buffer,
+    indices: Range<u32>,
+    targets: Vec<(Square, MarkerKind)>,
+    /// the contents of `marker_buffer`, so it's only written when something changed
+    uploaded: Vec<MarkerRaw>,
+}
+
+impl MarkersView {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> MarkersView {
+        let quad_vert =
+            device.create_shader_module(&wgpu::include_spirv!("shaders/marker.vert.spv"));
+        let quad_frag =
+            device.create_shader_module(&wgpu::include_spirv!("shaders/marker.frag.spv"));
+
+        let vert_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Marker Vertex Buffer"),
+            contents: bytemuck::cast_slice(VERTICES),
+            usage: wgpu::BufferUsage::VERTEX,
+        });
+
+        let idx_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Marker Index Buffer"),
+            contents: bytemuck::cast_slice(INDICES),
+            usage: wgpu::BufferUsage::INDEX,
+        });
+
+        let indices = 0..INDICES.len() as u32;
+
+        let marker_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Marker Instance Buffer"),
+            size: std::mem::size_of::<MarkersBuffer>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Markers Render Pipeline Layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Markers Render Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &quad_vert,
+                entry_point: "main",
+                buffers: &[LAYOUT, MarkerRaw::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &quad_frag,
+                entry_point: "main",
+                targets: &[wgpu::ColorTargetState {
+                    format,
+                    alpha_blend: wgpu::BlendState {
+                        src_factor: wgpu::BlendFactor::SrcAlpha,
+                        dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                    color_blend: wgpu::BlendState {
+                        src_factor: wgpu::BlendFactor::SrcAlpha,
+                        dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                    write_mask: wgpu::ColorWrite::ALL,
+                }],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: wgpu::CullMode::Back,
+                polygon_mode: wgpu::PolygonMode::Fill,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+        });
+
+        MarkersView {
+            pipeline,
+            vert_buffer,
+            idx_buffer,
+            marker_buffer,
+            indices,
+            targets: Vec::new(),
+            uploaded: Vec::new(),
+        }
+    }
+
+    /// marks `targets` with a dot, or a ring if they're in `captures`
+    pub fn set_targets(&mut self, targets: &[Square], captures: &[Square]) {
+        self.targets.clear();
+        for &square in targets {
+            if self.targets.iter().all(|&(target, _)| target != square) {
+                let kind = if captures.contains(&square) {
+                    MarkerKind::Ring
+                } else {
+                    MarkerKind::Dot
+                };
+                self.targets.push((square, kind));
+            }
+        }
+    }
+}
+
+impl Renderable for MarkersView {
+    fn prepare(&mut self, queue: &wgpu::Queue, _board: &Board, orientation: Orientation) {
+        let markers: Vec<_> = self
+            .targets
+            .iter()
+            .take(64)
+            .map(|&(square, kind)| MarkerRaw::new(square.oriented(orientation), kind))
+            .collect();
+        if markers != self.uploaded {
+            if !markers.is_empty() {
+                queue.write_buffer(&self.marker_buffer, 0, bytemuck::cast_slice(&markers));
+            }
+            self.uploaded = markers;
+        }
+    }
+
+    fn render<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if self.uploaded.is_empty() {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_vertex_buffer(0, self.vert_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, self.marker_buffer.slice(..));
+        render_pass.set_index_buffer(self.idx_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        render_pass.draw_indexed(self.indices.clone(), 0, 0..self.uploaded.len() as u32);
+    }
+}
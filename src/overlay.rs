@@ -0,0 +1,260 @@
+use std::ops::Range;
+
+use bytemuck::{Pod, Zeroable};
+use nalgebra::{Matrix4, Vector3, Vector4};
+use wgpu::util::DeviceExt;
+
+use crate::{
+    board::{Board, Orientation},
+    piece::{board_coord_to_world, Piece, PieceRaw},
+    promotion::{PromotionChooser, CHOICES},
+    quad::{INDICES, LAYOUT, VERTICES},
+    renderable::Renderable,
+};
+
+// the backdrop and one tile per promotion choice
+type ShapesBuffer = [OverlayRaw; 5];
+
+const BACKDROP_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.5];
+const TILE_COLOR: [f32; 4] = [0.6, 0.6, 0.6, 1.0];
+const HIGHLIGHT_COLOR: [f32; 4] = [0.85, 0.35, 0.02, 1.0];
+const PIECE_SCALE: f32 = 0.85;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverlayShape {
+    Rect,
+    Disc,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct OverlayRaw {
+    position: Matrix4<f32>,
+    color: Vector4<f32>,
+    shape: u32,
+}
+
+impl OverlayRaw {
+    pub fn new(position: Matrix4<f32>, color: [f32; 4], shape: OverlayShape) -> OverlayRaw {
+        OverlayRaw {
+            position,
+            color: color.into(),
+            shape: shape as u32,
+        }
+    }
+
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        use std::mem;
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<OverlayRaw>() as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Float4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 12]>() as wgpu::BufferAddress,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Float4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<Matrix4<f32>>() as wgpu::BufferAddress,
+                    shader_location: 6,
+                    format: wgpu::VertexFormat::Float4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 20]>() as wgpu::BufferAddress,
+                    shader_location: 7,
+                    format: wgpu::VertexFormat::Uint,
+                },
+            ],
+        }
+    }
+}
+
+/// modal layer drawn over the board, currently just the promotion picker
+pub struct OverlayView {
+    pipeline: wgpu::RenderPipeline,
+    vert_buffer: wgpu::Buffer,
+    idx_buffer: wgpu::Buffer,
+    shape_buffer: wgpu::Buffer,
+    indices: Range<u32>,
+    promotion: Option<PromotionChooser>,
+    /// the contents of `shape_buffer`, so it's only written when something changed
+    uploaded: Vec<OverlayRaw>,
+}
+
+impl OverlayView {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> OverlayView {
+        let quad_vert =
+            device.create_shader_module(&wgpu::include_spirv!("shaders/overlay.vert.spv"));
+        let quad_frag =
+            device.create_shader_module(&wgpu::include_spirv!("shaders/overlay.frag.spv"));
+
+        let vert_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Overlay Vertex Buffer"),
+            contents: bytemuck::cast_slice(VERTICES),
+            usage: wgpu::BufferUsage::VERTEX,
+        });
+
+        let idx_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Overlay Index Buffer"),
+            contents: bytemuck::cast_slice(INDICES),
+            usage: wgpu::BufferUsage::INDEX,
+        });
+
+        let indices = 0..INDICES.len() as u32;
+
+        let shape_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Overlay Shape Buffer"),
+            size: std::mem::size_of::<ShapesBuffer>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Overlay Render Pipeline Layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Overlay Render Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &quad_vert,
+                entry_point: "main",
+                buffers: &[LAYOUT, OverlayRaw::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &quad_frag,
+                entry_point: "main",
+                targets: &[wgpu::ColorTargetState {
+                    format,
+                    alpha_blend: wgpu::BlendState {
+                        src_factor: wgpu::BlendFactor::SrcAlpha,
+                        dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                    color_blend: wgpu::BlendState {
+                        src_factor: wgpu::BlendFactor::SrcAlpha,
+                        dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                    write_mask: wgpu::ColorWrite::ALL,
+                }],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: wgpu::CullMode::Back,
+                polygon_mode: wgpu::PolygonMode::Fill,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+        });
+
+        OverlayView {
+            pipeline,
+            vert_buffer,
+            idx_buffer,
+            shape_buffer,
+            indices,
+            promotion: None,
+            uploaded: Vec::new(),
+        }
+    }
+
+    pub fn set_promotion(&mut self, promotion: Option<PromotionChooser>) {
+        self.promotion = promotion;
+    }
+
+    /// the pieces to draw on top of the overlay, slightly smaller than usual to fit their tiles
+    pub fn pieces(&self, orientation: Orientation) -> Vec<PieceRaw> {
+        let promotion = match self.promotion {
+            Some(promotion) => promotion,
+            None => return Vec::new(),
+        };
+        CHOICES
+            .iter()
+            .zip(promotion.squares().iter())
+            .map(|(&type_, square)| {
+                let square = square.oriented(orientation);
+                let transform =
+                    Matrix4::new_scaling(PIECE_SCALE / 8.0).append_translation(&Vector3::new(
+                        board_coord_to_world(square.file as f32),
+                        board_coord_to_world(square.rank as f32),
+                        0.0,
+                    ));
+                Piece::new(promotion.color, type_).to_raw_transformed(transform, 1.0)
+            })
+            .collect()
+    }
+}
+
+impl Renderable for OverlayView {
+    fn prepare(&mut self, queue: &wgpu::Queue, _board: &Board, orientation: Orientation) {
+        let promotion = match self.promotion {
+            Some(promotion) => promotion,
+            None => {
+                self.uploaded.clear();
+                return;
+            }
+        };
+        let mut shapes = vec![OverlayRaw::new(
+            Matrix4::identity(),
+            BACKDROP_COLOR,
+            OverlayShape::Rect,
+        )];
+        for &square in promotion.squares().iter() {
+            let color = if square == promotion.highlighted_square() {
+                HIGHLIGHT_COLOR
+            } else {
+                TILE_COLOR
+            };
+            let square = square.oriented(orientation);
+            let transform = Matrix4::new_scaling(1.0 / 8.0).append_translation(&Vector3::new(
+                board_coord_to_world(square.file as f32),
+                board_coord_to_world(square.rank as f32),
+                0.0,
+            ));
+            shapes.push(OverlayRaw::new(transform, color, OverlayShape::Disc));
+        }
+        if shapes != self.uploaded {
+            queue.write_buffer(&self.shape_buffer, 0, bytemuck::cast_slice(&shapes));
+            self.uploaded = shapes;
+        }
+    }
+
+    fn render<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if self.uploaded.is_empty() {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_vertex_buffer(0, self.vert_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, self.shape_buffer.slice(..));
+        render_pass.set_index_buffer(self.idx_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        render_pass.draw_indexed(self.indices.clone(), 0, 0..self.uploaded.len() as u32);
+    }
+}
@@ -0,0 +1,418 @@
+use thiserror::Error;
+
+use crate::{
+    board::Square,
+    piece::Type,
+    rules::{FenError, Move, Position},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum PgnError {
+    #[error("game {game} has an invalid FEN tag: {source}")]
+    Fen { game: usize, source: FenError },
+    #[error("game {game}: {san} isn't a legal move at ply {ply}")]
+    IllegalMove {
+        /// counted from 1, as are plies
+        game: usize,
+        ply: usize,
+        san: String,
+    },
+}
+
+/// a game of a pgn file, with its moves checked against the rules
+#[derive(Debug, Clone, PartialEq)]
+pub struct Game {
+    /// the tag pairs in the order they were written, e.g. `("White", "Carlsen")`
+    pub tags: Vec<(String, String)>,
+    /// the start position, which is only set up differently by a `FEN` tag
+    pub start: Position,
+    pub moves: Vec<Move>,
+    /// the comments after each ply, starting with the one before the first move, so there's
+    /// one more than there are moves
+    pub comments: Vec<String>,
+}
+
+impl Game {
+    fn new() -> Game {
+        Game {
+            tags: Vec::new(),
+            start: Position::default(),
+            moves: Vec::new(),
+            comments: vec![String::new()],
+        }
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// the position after the first `ply` moves, or after all of them if there aren't as many
+    pub fn position_at(&self, ply: usize) -> Position {
+        let mut position = self.start;
+        for &mv in self.moves.iter().take(ply) {
+            position.play(mv);
+        }
+        position
+    }
+}
+
+/// parses every game in `pgn`. variations, annotation glyphs and marks like `!?` written apart
+/// from their move are skipped, and comments are kept with the ply they follow
+pub fn parse(pgn: &str) -> Result<Vec<Game>, PgnError> {
+    let mut games = Vec::new();
+    let mut game = Game::new();
+    let mut position = game.start;
+    let mut has_moves = false;
+
+    let mut chars = pgn.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        match c {
+            '[' => {
+                // a tag after the moves means the previous game had no result
+                if has_moves {
+                    games.push(game);
+                    game = Game::new();
+                    has_moves = false;
+                }
+                let end = tag_end(pgn, start);
+                if let Some(tag) = parse_tag(&pgn[start + 1..end]) {
+                    if tag.0 == "FEN" {
+                        game.start =
+                            Position::from_fen(&tag.1).map_err(|source| PgnError::Fen {
+                                game: games.len() + 1,
+                                source,
+                            })?;
+                    }
+                    game.tags.push(tag);
+                }
+                position = game.start;
+                skip_to(&mut chars, end);
+            }
+            '{' => {
+                let end = pgn[start..].find('}').map_or(pgn.len(), |end| start + end);
+                let comment = game.comments.last_mut().unwrap();
+                if !comment.is_empty() {
+                    comment.push(' ');
+                }
+                comment.push_str(pgn[start + 1..end].trim());
+                skip_to(&mut chars, end);
+            }
+            ';' => {
+                let end = pgn[start..].find('\n').map_or(pgn.len(), |end| start + end);
+                skip_to(&mut chars, end);
+            }
+            '(' => {
+                let mut depth = 1;
+                while depth > 0 {
+                    match chars.next() {
+                        Some((_, '(')) => depth += 1,
+                        Some((_, ')')) => depth -= 1,
+                        Some((i, '{')) => {
+                            let end = pgn[i..].find('}').map_or(pgn.len(), |end| i + end);
+                            skip_to(&mut chars, end);
+                        }
+                        Some(_) => {}
+                        None => break,
+                    }
+                }
+            }
+            c if c.is_whitespace() => {}
+            _ => {
+                let mut end = pgn.len();
+                while let Some(&(i, c)) = chars.peek() {
+                    if c.is_whitespace() || "[{;()".contains(c) {
+                        end = i;
+                        break;
+                    }
+                    chars.next();
+                }
+                let token = &pgn[start..end];
+                match token {
+                    "1-0" | "0-1" | "1/2-1/2" | "*" => {
+                        games.push(game);
+                        game = Game::new();
+                        position = game.start;
+                        has_moves = false;
+                    }
+                    _ if token.starts_with('$') || token.chars().all(|c| c == '!' || c == '?') => {}
+                    _ => {
+                        // move numbers may be written right against the move, as in `1.e4`
+                        let san =
+                            token.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+                        if san.is_empty() {
+                            continue;
+                        }
+                        let mv =
+                            parse_san(&position, san).ok_or_else(|| PgnError::IllegalMove {
+                                game: games.len() + 1,
+                                ply: game.moves.len() + 1,
+                                san: san.to_owned(),
+                            })?;
+                        position.play(mv);
+                        game.moves.push(mv);
+                        game.comments.push(String::new());
+                        has_moves = true;
+                    }
+                }
+            }
+        }
+    }
+    if has_moves || !game.tags.is_empty() {
+        games.push(game);
+    }
+    Ok(games)
+}
+
+/// advances `chars` past the byte offset `end`
+fn skip_to(chars: &mut std::iter::Peekable<std::str::CharIndices>, end: usize) {
+    while matches!(chars.peek(), Some(&(i, _)) if i <= end) {
+        chars.next();
+    }
+}
+
+/// the byte offset of the `]` closing the tag opened at `start`, which may also be written
+/// inside its value
+fn tag_end(pgn: &str, start: usize) -> usize {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in pgn[start..].char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ']' if !quoted => return start + i,
+            _ => {}
+        }
+    }
+    pgn.len()
+}
+
+/// the name and value of a tag such as `Event "Casual game"`
+fn parse_tag(tag: &str) -> Option<(String, String)> {
+    let tag = tag.trim();
+    let split = tag.find(char::is_whitespace)?;
+    let value = tag[split..].trim();
+    let value = value.strip_prefix('"')?.strip_suffix('"')?;
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.extend(chars.next()),
+            c => unescaped.push(c),
+        }
+    }
+    Some((tag[..split].to_owned(), unescaped))
+}
+
+/// finds the legal move written in standard algebraic notation, such as `Nbd7`, `exd8=Q+`
+/// or `O-O`
+pub fn parse_san(position: &Position, san: &str) -> Option<Move> {
+    let san = san.trim_end_matches(|c| "+#!?".contains(c));
+    // everything below slices by bytes
+    if !san.is_ascii() {
+        return None;
+    }
+    let moves = position.legal_moves();
+
+    if san == "O-O" || san == "0-0" || san == "O-O-O" || san == "0-0-0" {
+        let king = position.king(position.turn)?;
+        let file = if san.len() == 3 { 6 } else { 2 };
+        let to = Square::new(file, king.rank);
+        return moves.into_iter().find(|mv| mv.from == king && mv.to == to);
+    }
+
+    let (type_, rest) = match san.chars().next()? {
+        'K' => (Type::King, &san[1..]),
+        'Q' => (Type::Queen, &san[1..]),
+        'R' => (Type::Rook, &san[1..]),
+        'B' => (Type::Bishop, &san[1..]),
+        'N' => (Type::Knight, &san[1..]),
+        _ => (Type::Pawn, san),
+    };
+
+    let (rest, promotion) = match rest.rfind(|c: char| "QRBN".contains(c)) {
+        Some(i) if type_ == Type::Pawn => {
+            let promotion = match &rest[i..] {
+                "Q" => Type::Queen,
+                "R" => Type::Rook,
+                "B" => Type::Bishop,
+                "N" => Type::Knight,
+                _ => return None,
+            };
+            (rest[..i].trim_end_matches('='), Some(promotion))
+        }
+        _ => (rest, None),
+    };
+
+    let rest: String = rest.chars().filter(|&c| c != 'x' && c != '-').collect();
+    if rest.len() < 2 {
+        return None;
+    }
+    let to = Square::from_name(&rest[rest.len() - 2..])?;
+    let disambiguation = &rest[..rest.len() - 2];
+    let file = disambiguation
+        .chars()
+        .find(|c| ('a'..='h').contains(c))
+        .map(|c| c as usize - 'a' as usize);
+    let rank = disambiguation
+        .chars()
+        .find(|c| ('1'..='8').contains(c))
+        .map(|c| c as usize - '1' as usize);
+
+    let mut candidates = moves.into_iter().filter(|mv| {
+        mv.to == to
+            && mv.promotion == promotion
+            && matches!(position.piece(mv.from), Some(piece) if piece.type_ == type_)
+            && (file.is_none() || file == Some(mv.from.file))
+            && (rank.is_none() || rank == Some(mv.from.rank))
+    });
+    let mv = candidates.next()?;
+    // an ambiguous move is as wrong as one that can't be played
+    match candidates.next() {
+        Some(_) => None,
+        None => Some(mv),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(name: &str) -> Square {
+        Square::from_name(name).unwrap()
+    }
+
+    fn from_fen(fen: &str) -> Position {
+        Position::from_fen(fen).unwrap()
+    }
+
+    /// the position after playing `moves` from the start
+    fn after(moves: &str) -> Position {
+        parse(moves).unwrap()[0].position_at(usize::MAX)
+    }
+
+    #[test]
+    fn disambiguation() {
+        let knights = after("1. d4 d5 2. Nf3 Nf6 3. e3");
+        let from = |san| parse_san(&knights, san).map(|mv| mv.from);
+        assert_eq!(from("Nbd7"), Some(square("b8")));
+        assert_eq!(from("Nfd7"), Some(square("f6")));
+        assert_eq!(from("Nd7"), None);
+
+        let rooks = from_fen("4k3/8/8/8/8/4R3/8/4R1K1 w - - 0 1");
+        let from = |san| parse_san(&rooks, san).map(|mv| mv.from);
+        assert_eq!(from("R1e2"), Some(square("e1")));
+        assert_eq!(from("R3e2"), Some(square("e3")));
+        assert_eq!(from("Re1e2"), Some(square("e1")));
+        assert_eq!(from("Re2"), None);
+    }
+
+    #[test]
+    fn promotions() {
+        let position = from_fen("1n5k/P7/8/8/8/8/8/K7 w - - 0 1");
+        let promotion = |san| parse_san(&position, san).map(|mv| (mv.to, mv.promotion));
+        assert_eq!(promotion("a8=Q"), Some((square("a8"), Some(Type::Queen))));
+        assert_eq!(promotion("a8N"), Some((square("a8"), Some(Type::Knight))));
+        assert_eq!(promotion("axb8=R+"), Some((square("b8"), Some(Type::Rook))));
+        assert_eq!(promotion("axb8B"), Some((square("b8"), Some(Type::Bishop))));
+        assert_eq!(promotion("a8"), None);
+        assert_eq!(promotion("a8=K"), None);
+    }
+
+    #[test]
+    fn castling() {
+        let position = from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        let to = |san| parse_san(&position, san).map(|mv| mv.to);
+        assert_eq!(to("O-O"), Some(square("g1")));
+        assert_eq!(to("O-O-O+"), Some(square("c1")));
+        assert_eq!(to("0-0"), Some(square("g1")));
+        assert_eq!(to("0-0-0"), Some(square("c1")));
+
+        let no_rights = from_fen("r3k2r/8/8/8/8/8/8/R3K2R w - - 0 1");
+        assert_eq!(parse_san(&no_rights, "O-O"), None);
+    }
+
+    #[test]
+    fn comments_and_variations() {
+        let games = parse(
+            "{before} 1. e4 {best by test} e5 (1... c5 {sicilian} 2. Nf3 (2. c3)) 2.Nf3 $1 Nc6 ! \
+             ; a comment to the end of the line\n3. Bb5 !? a6 1-0",
+        )
+        .unwrap();
+        assert_eq!(games.len(), 1);
+        let game = &games[0];
+        assert_eq!(game.moves.len(), 6);
+        assert_eq!(game.comments.len(), 7);
+        assert_eq!(game.comments[0], "before");
+        assert_eq!(game.comments[1], "best by test");
+        assert!(game.comments[2..].iter().all(String::is_empty));
+        assert_eq!(game.moves[5], Move::new(square("a7"), square("a6")));
+    }
+
+    #[test]
+    fn multiple_games() {
+        let games = parse(
+            r#"[Event "Open [round 2]"]
+[White "\"Magnus\" Carlsen"]
+[Result "1-0"]
+
+1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0
+
+[Event "Endgame"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"]
+
+1. e4 Kd7
+
+[Event "Unfinished"]
+1. d4 *
+"#,
+        )
+        .unwrap();
+        assert_eq!(games.len(), 3);
+        assert_eq!(games[0].tag("Event"), Some("Open [round 2]"));
+        assert_eq!(games[0].tag("White"), Some("\"Magnus\" Carlsen"));
+        assert!(games[0].position_at(7).is_checkmate());
+        assert_eq!(games[1].start, from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"));
+        assert_eq!(games[1].moves.len(), 2);
+        assert_eq!(games[2].tag("Event"), Some("Unfinished"));
+        assert_eq!(games[2].moves.len(), 1);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            parse("1. e4 e5 2. Ke3"),
+            Err(PgnError::IllegalMove {
+                game: 1,
+                ply: 3,
+                san: "Ke3".to_owned()
+            })
+        );
+        assert_eq!(
+            parse("1. e4 1-0 1. d4 d5 2. Nd3"),
+            Err(PgnError::IllegalMove {
+                game: 2,
+                ply: 3,
+                san: "Nd3".to_owned()
+            })
+        );
+        assert!(matches!(
+            parse("[FEN \"8/8/8/8/8/8/8/8 w - -\"]\n[FEN \"bad\"]"),
+            Err(PgnError::Fen { game: 1, .. })
+        ));
+        assert_eq!(
+            parse("1. e€"),
+            Err(PgnError::IllegalMove {
+                game: 1,
+                ply: 1,
+                san: "e€".to_owned()
+            })
+        );
+        for san in &["e€", "€4", "Né3", "exd€", "e8=♕"] {
+            assert_eq!(parse_san(&Position::default(), san), None);
+        }
+    }
+}
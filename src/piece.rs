@@ -1,7 +1,7 @@
 use bytemuck::{Pod, Zeroable};
 use nalgebra::{Matrix4, Vector2, Vector3};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Type {
     King,
     Queen,
@@ -11,20 +11,29 @@ pub enum Type {
     Pawn,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Color {
     Black,
     White,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+impl Color {
+    pub fn opposite(&self) -> Color {
+        match self {
+            Color::Black => Color::White,
+            Color::White => Color::Black,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Piece {
     pub color: Color,
     pub type_: Type,
 }
 
-fn board_coord_to_world(board: usize) -> f32 {
-    ((board as f32 + 0.5) / 8.0 * 2.0) - 1.0
+pub fn board_coord_to_world(board: f32) -> f32 {
+    ((board + 0.5) / 8.0 * 2.0) - 1.0
 }
 
 impl Piece {
@@ -33,13 +42,27 @@ impl Piece {
     }
 
     pub fn to_raw(&self, x: usize, y: usize) -> PieceRaw {
-        PieceRaw {
-            position: Matrix4::new_scaling(1.0 / 8.0).append_translation(&Vector3::new(
-                board_coord_to_world(x),
-                board_coord_to_world(y),
+        self.to_raw_at(Vector2::new(x as f32, y as f32), 1.0)
+    }
+
+    /// like `to_raw` but for pieces between squares (in the middle of a move) or fading out
+    pub fn to_raw_at(&self, position: Vector2<f32>, alpha: f32) -> PieceRaw {
+        self.to_raw_transformed(
+            Matrix4::new_scaling(1.0 / 8.0).append_translation(&Vector3::new(
+                board_coord_to_world(position.x),
+                board_coord_to_world(position.y),
                 0.0,
             )),
+            alpha,
+        )
+    }
+
+    /// places the piece's quad with an arbitrary transform, e.g. following the cursor
+    pub fn to_raw_transformed(&self, position: Matrix4<f32>, alpha: f32) -> PieceRaw {
+        PieceRaw {
+            position,
             types: Vector2::new(self.type_ as u16, self.color as u16),
+            alpha,
         }
     }
 }
@@ -49,6 +72,7 @@ impl Piece {
 pub struct PieceRaw {
     position: Matrix4<f32>,
     types: Vector2<u16>,
+    alpha: f32,
 }
 
 impl PieceRaw {
@@ -83,6 +107,12 @@ impl PieceRaw {
                     shader_location: 6,
                     format: wgpu::VertexFormat::Ushort2,
                 },
+                wgpu::VertexAttribute {
+                    offset: (mem::size_of::<Matrix4<f32>>() + mem::size_of::<[u16; 2]>())
+                        as wgpu::BufferAddress,
+                    shader_location: 7,
+                    format: wgpu::VertexFormat::Float,
+                },
             ],
         }
     }
@@ -0,0 +1,342 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use image::{imageops, GenericImageView, RgbaImage};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::piece::{Color, Piece, Type};
+
+/// columns of the packed atlas, in the order of `Type`
+const TYPES: [Type; 6] = [
+    Type::King,
+    Type::Queen,
+    Type::Bishop,
+    Type::Knight,
+    Type::Rook,
+    Type::Pawn,
+];
+
+/// rows of the packed atlas, top to bottom
+const COLORS: [Color; 2] = [Color::White, Color::Black];
+
+#[derive(Debug, Error)]
+pub enum PieceSetError {
+    #[error("unable to read {path}: {source}")]
+    Io { path: PathBuf, source: io::Error },
+    #[error("unable to decode {path}: {source}")]
+    Image {
+        path: PathBuf,
+        source: image::ImageError,
+    },
+    #[error("invalid atlas descriptor {path}: {source}")]
+    Descriptor {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[error("no image for {0}")]
+    Missing(String),
+    #[error("{name} is {width}x{height}, pieces must all be {size}x{size} squares")]
+    Dimensions {
+        name: String,
+        width: u32,
+        height: u32,
+        size: u32,
+    },
+    #[error("unable to parse {path}: {source}")]
+    Svg { path: PathBuf, source: usvg::Error },
+    #[error("{name} is outside of the {columns}x{rows} atlas grid")]
+    OutOfGrid {
+        name: String,
+        columns: u32,
+        rows: u32,
+    },
+    #[error("{path} describes a {columns}x{rows} grid, it needs at least one column and row")]
+    EmptyGrid {
+        path: PathBuf,
+        columns: u32,
+        rows: u32,
+    },
+}
+
+/// the name of a piece's image, e.g. `wK` for the white king
+pub fn piece_name(piece: Piece) -> String {
+    let color = match piece.color {
+        Color::White => 'w',
+        Color::Black => 'b',
+    };
+    let type_ = match piece.type_ {
+        Type::King => 'K',
+        Type::Queen => 'Q',
+        Type::Bishop => 'B',
+        Type::Knight => 'N',
+        Type::Rook => 'R',
+        Type::Pawn => 'P',
+    };
+    format!("{}{}", color, type_)
+}
+
+pub(crate) fn all_pieces() -> impl Iterator<Item = Piece> {
+    COLORS
+        .iter()
+        .flat_map(|&color| TYPES.iter().map(move |&type_| Piece::new(color, type_)))
+}
+
+/// describes an atlas that doesn't use the default layout, for example
+///
+/// ```toml
+/// image = "pieces.png"
+/// columns = 6
+/// rows = 2
+///
+/// [pieces]
+/// wK = [0, 0]
+/// bK = [0, 1]
+/// # ...and so on for all twelve pieces, as [column, row]
+/// ```
+#[derive(Debug, Deserialize)]
+struct AtlasDescriptor {
+    image: PathBuf,
+    columns: u32,
+    rows: u32,
+    pieces: HashMap<String, [u32; 2]>,
+}
+
+/// the twelve piece images packed into the 6x2 atlas layout the piece shader expects,
+/// white on the top row and the columns in the order of `Type`
+#[derive(Debug, Clone)]
+pub struct PieceSet {
+    atlas: RgbaImage,
+    cell_size: u32,
+}
+
+impl PieceSet {
+    /// the set built into the binary
+    pub fn builtin() -> Result<PieceSet, PieceSetError> {
+        let atlas = image::load_from_memory(include_bytes!("images/pieces.png"))
+            .map_err(|source| PieceSetError::Image {
+                path: PathBuf::from("images/pieces.png"),
+                source,
+            })?
+            .into_rgba8();
+        let cell_size = atlas.width() / TYPES.len() as u32;
+        Ok(PieceSet { atlas, cell_size })
+    }
+
+    pub fn atlas(&self) -> &RgbaImage {
+        &self.atlas
+    }
+
+    /// the width and height of a single piece in the atlas
+    pub fn cell_size(&self) -> u32 {
+        self.cell_size
+    }
+
+    /// the image of a single piece, cut out of the atlas
+    pub fn image(&self, piece: Piece) -> RgbaImage {
+        let column = TYPES
+            .iter()
+            .position(|&type_| type_ == piece.type_)
+            .unwrap() as u32;
+        let row = COLORS
+            .iter()
+            .position(|&color| color == piece.color)
+            .unwrap() as u32;
+        let size = self.cell_size;
+        self.atlas
+            .view(column * size, row * size, size, size)
+            .to_image()
+    }
+
+    /// packs already decoded images, which must all be square and the same size
+    pub fn from_images(mut images: HashMap<Piece, RgbaImage>) -> Result<PieceSet, PieceSetError> {
+        let mut cell_size = None;
+        let mut atlas = RgbaImage::new(0, 0);
+        for (column, &type_) in TYPES.iter().enumerate() {
+            for (row, &color) in COLORS.iter().enumerate() {
+                let piece = Piece::new(color, type_);
+                let image = images
+                    .remove(&piece)
+                    .ok_or_else(|| PieceSetError::Missing(piece_name(piece)))?;
+                let size = *cell_size.get_or_insert(image.width());
+                if image.width() != size || image.height() != size || size == 0 {
+                    return Err(PieceSetError::Dimensions {
+                        name: piece_name(piece),
+                        width: image.width(),
+                        height: image.height(),
+                        size,
+                    });
+                }
+                if atlas.width() == 0 {
+                    atlas = RgbaImage::new(size * TYPES.len() as u32, size * COLORS.len() as u32);
+                }
+                imageops::replace(&mut atlas, &image, column as u32 * size, row as u32 * size);
+            }
+        }
+        Ok(PieceSet {
+            atlas,
+            cell_size: cell_size.unwrap_or(0),
+        })
+    }
+
+    /// loads a directory with one png per piece named like `wK.png` or `bN.png`
+    pub fn load_dir(dir: impl AsRef<Path>) -> Result<PieceSet, PieceSetError> {
+        let dir = dir.as_ref();
+        let mut images = HashMap::new();
+        for piece in all_pieces() {
+            let path = dir.join(format!("{}.png", piece_name(piece)));
+            if !path.exists() {
+                return Err(PieceSetError::Missing(path.display().to_string()));
+            }
+            images.insert(piece, open_image(&path)?);
+        }
+        PieceSet::from_images(images)
+    }
+
+    /// loads an atlas described by a toml descriptor, see `AtlasDescriptor`
+    pub fn load_atlas(descriptor: impl AsRef<Path>) -> Result<PieceSet, PieceSetError> {
+        let path = descriptor.as_ref();
+        let contents = fs::read_to_string(path).map_err(|source| PieceSetError::Io {
+            path: path.to_owned(),
+            source,
+        })?;
+        let descriptor: AtlasDescriptor =
+            toml::from_str(&contents).map_err(|source| PieceSetError::Descriptor {
+                path: path.to_owned(),
+                source,
+            })?;
+        let (columns, rows) = (descriptor.columns, descriptor.rows);
+        if columns == 0 || rows == 0 {
+            return Err(PieceSetError::EmptyGrid {
+                path: path.to_owned(),
+                columns,
+                rows,
+            });
+        }
+        let image_path = path
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(&descriptor.image);
+        let atlas = open_image(&image_path)?;
+
+        let (cell_width, cell_height) = (atlas.width() / columns, atlas.height() / rows);
+        if cell_width != cell_height || atlas.width() % columns != 0 || atlas.height() % rows != 0 {
+            return Err(PieceSetError::Dimensions {
+                name: image_path.display().to_string(),
+                width: atlas.width(),
+                height: atlas.height(),
+                size: cell_width,
+            });
+        }
+
+        let mut images = HashMap::new();
+        for piece in all_pieces() {
+            let name = piece_name(piece);
+            let &[column, row] = descriptor
+                .pieces
+                .get(&name)
+                .ok_or_else(|| PieceSetError::Missing(name.clone()))?;
+            if column >= columns || row >= rows {
+                return Err(PieceSetError::OutOfGrid {
+                    name,
+                    columns,
+                    rows,
+                });
+            }
+            let cell = atlas
+                .view(
+                    column * cell_width,
+                    row * cell_height,
+                    cell_width,
+                    cell_height,
+                )
+                .to_image();
+            images.insert(piece, cell);
+        }
+        PieceSet::from_images(images)
+    }
+}
+
+fn open_image(path: &Path) -> Result<RgbaImage, PieceSetError> {
+    image::open(path)
+        .map(|image| image.into_rgba8())
+        .map_err(|source| PieceSetError::Image {
+            path: path.to_owned(),
+            source,
+        })
+}
+
+/// a piece set drawn from svgs, rasterized to exactly the size the pieces take up on screen
+/// so they stay sharp at any resolution
+#[derive(Clone)]
+pub struct SvgPieceSet {
+    trees: HashMap<Piece, usvg::Tree>,
+}
+
+impl SvgPieceSet {
+    /// loads a directory with one svg per piece named like `wK.svg` or `bN.svg`
+    pub fn load_dir(dir: impl AsRef<Path>) -> Result<SvgPieceSet, PieceSetError> {
+        let dir = dir.as_ref();
+        let options = usvg::Options {
+            resources_dir: Some(dir.to_owned()),
+            ..usvg::Options::default()
+        };
+        let mut trees = HashMap::new();
+        for piece in all_pieces() {
+            let path = dir.join(format!("{}.svg", piece_name(piece)));
+            if !path.exists() {
+                return Err(PieceSetError::Missing(path.display().to_string()));
+            }
+            let data = fs::read(&path).map_err(|source| PieceSetError::Io {
+                path: path.clone(),
+                source,
+            })?;
+            let tree = usvg::Tree::from_data(&data, &options.to_ref())
+                .map_err(|source| PieceSetError::Svg { path, source })?;
+            trees.insert(piece, tree);
+        }
+        Ok(SvgPieceSet { trees })
+    }
+
+    /// renders every piece into a `size`x`size` cell, centered if the svg isn't square
+    pub fn rasterize(&self, size: u32) -> PieceSet {
+        let size = size.max(1);
+        let images = self
+            .trees
+            .iter()
+            .map(|(&piece, tree)| (piece, rasterize_tree(tree, size)))
+            .collect();
+        PieceSet::from_images(images).expect("rasterized pieces are all the same size")
+    }
+
+    /// the svg document of `piece`, as simplified by usvg
+    pub fn to_svg(&self, piece: Piece) -> String {
+        self.trees[&piece].to_string(&usvg::XmlOptions::default())
+    }
+}
+
+fn rasterize_tree(tree: &usvg::Tree, size: u32) -> RgbaImage {
+    let svg_size = tree.svg_node().size;
+    let scale = size as f64 / svg_size.width().max(svg_size.height());
+    let (width, height) = (svg_size.width() * scale, svg_size.height() * scale);
+    let mut pixmap = tiny_skia::Pixmap::new(size, size).expect("size is never zero");
+    resvg::render(
+        tree,
+        usvg::FitTo::Size(size, size),
+        tiny_skia::Transform::from_translate(
+            ((size as f64 - width) / 2.0) as f32,
+            ((size as f64 - height) / 2.0) as f32,
+        ),
+        pixmap.as_mut(),
+    );
+    // tiny-skia works in premultiplied alpha but the piece shader blends straight alpha
+    let mut pixels = Vec::with_capacity(pixmap.data().len());
+    for pixel in pixmap.pixels() {
+        let color = pixel.demultiply();
+        pixels.extend_from_slice(&[color.red(), color.green(), color.blue(), color.alpha()]);
+    }
+    RgbaImage::from_raw(size, size, pixels).expect("the pixmap is size x size")
+}
@@ -1,16 +1,38 @@
-use std::ops::Range;
+use std::{
+    mem,
+    ops::Range,
+    time::{Duration, Instant},
+};
 
-use image::GenericImageView;
+use bytemuck::Zeroable;
+use nalgebra::{Matrix4, Vector2};
 use wgpu::util::DeviceExt;
 
 use crate::{
+    animation::Animation,
+    atlas::MipmappedAtlas,
+    board::{Orientation, Square},
     piece::PieceRaw,
+    piece_set::{PieceSet, PieceSetError},
     quad::{INDICES, LAYOUT, VERTICES},
     renderable::Renderable,
     Board,
 };
 
-type PiecesBuffer = [PieceRaw; 64];
+/// the first instances of the pieces buffer are one per square, in the order of the ranks
+/// then files with empty squares zeroed so they're never drawn, followed by the pieces being
+/// animated
+const SQUARES: usize = 64;
+
+// room for a full board plus as many pieces fading out or sliding
+type PiecesBuffer = [PieceRaw; SQUARES * 2];
+
+const OVERLAY_CAPACITY: usize = 8;
+
+type OverlayBuffer = [PieceRaw; OVERLAY_CAPACITY];
+
+/// opacity of the piece left behind on the origin square while dragging
+const GHOST_ALPHA: f32 = 0.3;
 
 pub struct PiecesView {
     pipeline: wgpu::RenderPipeline,
@@ -18,15 +40,34 @@ pub struct PiecesView {
     idx_buffer: wgpu::Buffer,
     piece_buffer: wgpu::Buffer,
     indices: Range<u32>,
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    texture_sampler: wgpu::Sampler,
     texture_bind_group: wgpu::BindGroup,
+    animation: Option<Animation>,
+    drag_buffer: wgpu::Buffer,
+    overlay_buffer: wgpu::Buffer,
+    /// the square of the piece being dragged and where it's drawn instead
+    dragged: Option<(Square, Matrix4<f32>)>,
+    /// the squares in `piece_buffer`, so only the ones that changed are written, `None`
+    /// until the first upload
+    uploaded_squares: Option<[PieceRaw; SQUARES]>,
+    /// the contents of `drag_buffer`
+    uploaded_drag: Option<PieceRaw>,
+    /// how many instances of each kind were written by the last `prepare`
+    fade_count: u32,
+    tween_count: u32,
+    drag_count: u32,
+    /// the contents of `overlay_buffer`
+    uploaded_overlay: Vec<PieceRaw>,
 }
 
 impl PiecesView {
     pub fn new(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
-        sc_desc: &wgpu::SwapChainDescriptor,
-    ) -> PiecesView {
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Result<PiecesView, PieceSetError> {
         let quad_vert =
             device.create_shader_module(&wgpu::include_spirv!("shaders/piece.vert.spv"));
         let quad_frag =
@@ -53,52 +94,27 @@ impl PiecesView {
             mapped_at_creation: false,
         });
 
-        let mut texture_image =
-            image::load_from_memory(include_bytes!("images/pieces.png")).unwrap();
-        image::imageops::flip_vertical_in_place(&mut texture_image);
-        let texture_rgba = texture_image.as_rgba8().unwrap();
-        let dimensions = texture_image.dimensions();
-
-        let texture_size = wgpu::Extent3d {
-            width: dimensions.0,
-            height: dimensions.1,
-            depth: 1,
-        };
-
-        let texture_texture = device.create_texture(&wgpu::TextureDescriptor {
-            size: texture_size,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
-            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
-            label: Some("Pieces Diffuse Texture"),
+        let drag_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Piece Drag Buffer"),
+            size: std::mem::size_of::<PieceRaw>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
         });
 
-        queue.write_texture(
-            wgpu::TextureCopyView {
-                texture: &texture_texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-            },
-            texture_rgba,
-            wgpu::TextureDataLayout {
-                offset: 0,
-                bytes_per_row: 4 * dimensions.0,
-                rows_per_image: dimensions.1,
-            },
-            texture_size,
-        );
+        let overlay_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Piece Overlay Buffer"),
+            size: std::mem::size_of::<OverlayBuffer>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
 
-        let texture_texture_view =
-            texture_texture.create_view(&wgpu::TextureViewDescriptor::default());
         let texture_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
             address_mode_u: wgpu::AddressMode::ClampToEdge,
             address_mode_v: wgpu::AddressMode::ClampToEdge,
             address_mode_w: wgpu::AddressMode::ClampToEdge,
             mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
             ..Default::default()
         });
 
@@ -111,7 +127,7 @@ impl PiecesView {
                         ty: wgpu::BindingType::Texture {
                             multisampled: false,
                             view_dimension: wgpu::TextureViewDimension::D2,
-                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
                         },
                         count: None,
                     },
@@ -124,24 +140,27 @@ impl PiecesView {
                         },
                         count: None,
                     },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStage::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
                 ],
                 label: Some("Pieces Texture Bind Group Layout"),
             });
 
-        let texture_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &texture_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&texture_texture_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&texture_sampler),
-                },
-            ],
-            label: Some("Pieces Texture Bind Group"),
-        });
+        let texture_bind_group = create_texture_bind_group(
+            device,
+            queue,
+            &texture_bind_group_layout,
+            &texture_sampler,
+            &PieceSet::builtin()?,
+        );
 
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("Pieces Render Pipeline Layout"),
@@ -161,7 +180,7 @@ impl PiecesView {
                 module: &quad_frag,
                 entry_point: "main",
                 targets: &[wgpu::ColorTargetState {
-                    format: sc_desc.format,
+                    format,
                     alpha_blend: wgpu::BlendState {
                         src_factor: wgpu::BlendFactor::SrcAlpha,
                         dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
@@ -184,57 +203,295 @@ impl PiecesView {
             },
             depth_stencil: None,
             multisample: wgpu::MultisampleState {
-                count: 1,
+                count: sample_count,
                 mask: !0,
                 alpha_to_coverage_enabled: false,
             },
         });
 
-        PiecesView {
+        Ok(PiecesView {
             pipeline,
             vert_buffer,
             idx_buffer,
             piece_buffer,
             indices,
+            texture_bind_group_layout,
+            texture_sampler,
             texture_bind_group,
+            animation: None,
+            drag_buffer,
+            overlay_buffer,
+            dragged: None,
+            uploaded_squares: None,
+            uploaded_drag: None,
+            fade_count: 0,
+            tween_count: 0,
+            drag_count: 0,
+            uploaded_overlay: Vec::new(),
+        })
+    }
+
+    /// draws the piece on `square` with `transform` on top of everything else, leaving a
+    /// ghost of it behind
+    pub fn set_drag(&mut self, square: Square, transform: Matrix4<f32>) {
+        self.dragged = Some((square, transform));
+    }
+
+    pub fn clear_drag(&mut self) {
+        self.dragged = None;
+    }
+
+    /// writes pieces that aren't on the board, such as the choices of the promotion picker
+    pub fn prepare_overlay(&mut self, queue: &wgpu::Queue, pieces: &[PieceRaw]) {
+        let pieces = &pieces[..pieces.len().min(OVERLAY_CAPACITY)];
+        if pieces != self.uploaded_overlay.as_slice() {
+            if !pieces.is_empty() {
+                queue.write_buffer(&self.overlay_buffer, 0, bytemuck::cast_slice(pieces));
+            }
+            self.uploaded_overlay = pieces.to_vec();
+        }
+    }
+
+    /// draws the dragged piece, kept separate from `render` so it can go over the other layers
+    pub fn render_drag<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        self.draw(render_pass, &self.drag_buffer, 0..self.drag_count);
+    }
+
+    /// draws the pieces written by `prepare_overlay`
+    pub fn render_overlay<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        let count = self.uploaded_overlay.len() as u32;
+        self.draw(render_pass, &self.overlay_buffer, 0..count);
+    }
+
+    fn draw<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        buffer: &'a wgpu::Buffer,
+        instances: Range<u32>,
+    ) {
+        if instances.start == instances.end {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.texture_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vert_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, buffer.slice(..));
+        render_pass.set_index_buffer(self.idx_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        render_pass.draw_indexed(self.indices.clone(), 0, instances);
+    }
+
+    /// writes the runs of squares that differ from what's already in the buffer
+    fn upload_squares(&mut self, queue: &wgpu::Queue, squares: &[PieceRaw; SQUARES]) {
+        let uploaded = self.uploaded_squares;
+        let unchanged = |i: usize| matches!(&uploaded, Some(uploaded) if uploaded[i] == squares[i]);
+        let mut start = 0;
+        while start < SQUARES {
+            if unchanged(start) {
+                start += 1;
+                continue;
+            }
+            let mut end = start + 1;
+            while end < SQUARES && !unchanged(end) {
+                end += 1;
+            }
+            queue.write_buffer(
+                &self.piece_buffer,
+                (start * mem::size_of::<PieceRaw>()) as wgpu::BufferAddress,
+                bytemuck::cast_slice(&squares[start..end]),
+            );
+            start = end;
+        }
+        self.uploaded_squares = Some(*squares);
+    }
+
+    /// swaps the piece images, the pipeline stays the same so this is cheap enough to do at
+    /// any time
+    pub fn set_piece_set(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, set: &PieceSet) {
+        self.texture_bind_group = create_texture_bind_group(
+            device,
+            queue,
+            &self.texture_bind_group_layout,
+            &self.texture_sampler,
+            set,
+        );
+    }
+
+    /// starts animating the pieces from `old` to `new`, replacing any running animation
+    pub fn animate(&mut self, old: &Board, new: &Board, duration: Duration) {
+        self.animation = Animation::between(old, new, duration);
+    }
+
+    /// stops animating the piece moving to `to`, leaving the rest of the animation running
+    pub fn skip_tween(&mut self, to: Square) {
+        if let Some(animation) = &mut self.animation {
+            animation.skip_tween(to);
+        }
+    }
+
+    pub fn is_animating(&self) -> bool {
+        match &self.animation {
+            Some(animation) => !animation.is_finished(Instant::now()),
+            None => false,
         }
     }
 }
 
+/// uploads the atlas of `set` with its mip chain and binds it along with `sampler`
+fn create_texture_bind_group(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    layout: &wgpu::BindGroupLayout,
+    sampler: &wgpu::Sampler,
+    set: &PieceSet,
+) -> wgpu::BindGroup {
+    let atlas = MipmappedAtlas::new(set);
+    let dimensions = atlas.levels[0].dimensions();
+
+    let texture_texture = device.create_texture(&wgpu::TextureDescriptor {
+        size: wgpu::Extent3d {
+            width: dimensions.0,
+            height: dimensions.1,
+            depth: 1,
+        },
+        mip_level_count: atlas.levels.len() as u32,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+        usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+        label: Some("Pieces Diffuse Texture"),
+    });
+
+    for (mip_level, level) in atlas.levels.iter().enumerate() {
+        let dimensions = level.dimensions();
+        queue.write_texture(
+            wgpu::TextureCopyView {
+                texture: &texture_texture,
+                mip_level: mip_level as u32,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            level,
+            wgpu::TextureDataLayout {
+                offset: 0,
+                bytes_per_row: 4 * dimensions.0,
+                rows_per_image: dimensions.1,
+            },
+            wgpu::Extent3d {
+                width: dimensions.0,
+                height: dimensions.1,
+                depth: 1,
+            },
+        );
+    }
+
+    let texture_texture_view = texture_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    let atlas_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Pieces Atlas Uniform Buffer"),
+        contents: bytemuck::bytes_of(&atlas.uniform),
+        usage: wgpu::BufferUsage::UNIFORM,
+    });
+
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&texture_texture_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: atlas_buffer.as_entire_binding(),
+            },
+        ],
+        label: Some("Pieces Texture Bind Group"),
+    })
+}
+
 impl Renderable for PiecesView {
-    fn render(
-        &self,
-        encoder: &mut wgpu::CommandEncoder,
-        queue: &mut wgpu::Queue,
-        frame: &wgpu::SwapChainTexture,
-        board: &Board,
-    ) {
-        let mut pieces = Vec::new();
+    fn prepare(&mut self, queue: &wgpu::Queue, board: &Board, orientation: Orientation) {
+        let now = Instant::now();
+        let animation = self
+            .animation
+            .as_ref()
+            .filter(|animation| !animation.is_finished(now));
+
+        let mut squares = [PieceRaw::zeroed(); SQUARES];
         for (y, row) in board.iter().rev().enumerate() {
             for (x, piece) in row.iter().enumerate() {
-                if let Some(piece) = piece {
-                    pieces.push(piece.to_raw(x, y));
+                let square = Square::new(x, y);
+                let is_moving = matches!(animation, Some(a) if a.is_tween_target(square));
+                let is_dragged = matches!(self.dragged, Some((dragged, _)) if dragged == square);
+                if let (Some(piece), false) = (piece, is_moving) {
+                    let alpha = if is_dragged { GHOST_ALPHA } else { 1.0 };
+                    let oriented = square.oriented(orientation);
+                    squares[y * 8 + x] = piece.to_raw_at(
+                        Vector2::new(oriented.file as f32, oriented.rank as f32),
+                        alpha,
+                    );
                 }
             }
         }
-        queue.write_buffer(&self.piece_buffer, 0, bytemuck::cast_slice(&pieces));
-        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("Pieces Render Pass"),
-            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                attachment: &frame.view,
-                resolve_target: None,
-                ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Load,
-                    store: true,
-                },
-            }],
-            depth_stencil_attachment: None,
+
+        // every animated piece was on the old board, so they always fit after the squares.
+        // they move every frame so there's no point diffing them
+        let mut animated = Vec::new();
+        if let Some(animation) = animation {
+            let progress = animation.progress(now);
+            for fade in &animation.fades {
+                animated.push(
+                    fade.piece
+                        .to_raw_at(fade.position(orientation), 1.0 - progress),
+                );
+            }
+            for tween in &animation.tweens {
+                animated.push(
+                    tween
+                        .piece
+                        .to_raw_at(tween.position(progress, orientation), 1.0),
+                );
+            }
+            self.fade_count = animation.fades.len() as u32;
+            self.tween_count = animation.tweens.len() as u32;
+        } else {
+            self.fade_count = 0;
+            self.tween_count = 0;
+        }
+        if !animated.is_empty() {
+            queue.write_buffer(
+                &self.piece_buffer,
+                (SQUARES * mem::size_of::<PieceRaw>()) as wgpu::BufferAddress,
+                bytemuck::cast_slice(&animated),
+            );
+        }
+
+        self.upload_squares(queue, &squares);
+
+        let dragged = self.dragged.and_then(|(square, transform)| {
+            Some(square.piece(board)?.to_raw_transformed(transform, 1.0))
         });
-        render_pass.set_pipeline(&self.pipeline);
-        render_pass.set_bind_group(0, &self.texture_bind_group, &[]);
-        render_pass.set_vertex_buffer(0, self.vert_buffer.slice(..));
-        render_pass.set_vertex_buffer(1, self.piece_buffer.slice(..));
-        render_pass.set_index_buffer(self.idx_buffer.slice(..), wgpu::IndexFormat::Uint16);
-        render_pass.draw_indexed(self.indices.clone(), 0, 0..pieces.len() as u32);
+        if let Some(raw) = dragged {
+            if self.uploaded_drag != Some(raw) {
+                queue.write_buffer(&self.drag_buffer, 0, bytemuck::bytes_of(&raw));
+                self.uploaded_drag = Some(raw);
+            }
+        }
+        self.drag_count = dragged.is_some() as u32;
+    }
+
+    fn render<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        let squares = SQUARES as u32;
+        let fades_end = squares + self.fade_count;
+        // fading pieces go under the board's pieces and sliding ones over them
+        self.draw(render_pass, &self.piece_buffer, squares..fades_end);
+        self.draw(render_pass, &self.piece_buffer, 0..squares);
+        self.draw(
+            render_pass,
+            &self.piece_buffer,
+            fades_end..fades_end + self.tween_count,
+        );
     }
 }
@@ -0,0 +1,73 @@
+use crate::{
+    board::Square,
+    piece::{Color, Type},
+};
+
+/// the pieces a pawn can promote to, in the order they're offered
+pub const CHOICES: [Type; 4] = [Type::Queen, Type::Knight, Type::Rook, Type::Bishop];
+
+/// state of the promotion picker, shown as a column of pieces extending from the
+/// promotion square towards the middle of the board
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PromotionChooser {
+    pub from: Square,
+    pub to: Square,
+    pub color: Color,
+    highlighted: usize,
+}
+
+impl PromotionChooser {
+    /// a chooser for the pawn of `color` moving to `to`, `None` unless `to` is on the rank
+    /// that color promotes on
+    pub fn new(from: Square, to: Square, color: Color) -> Option<PromotionChooser> {
+        let last_rank = match color {
+            Color::White => 7,
+            Color::Black => 0,
+        };
+        if to.rank != last_rank {
+            return None;
+        }
+        Some(PromotionChooser {
+            from,
+            to,
+            color,
+            highlighted: 0,
+        })
+    }
+
+    /// the square each entry of `CHOICES` is drawn on
+    pub fn squares(&self) -> [Square; 4] {
+        let mut squares = [self.to; 4];
+        for (i, square) in squares.iter_mut().enumerate() {
+            square.rank = match self.color {
+                Color::White => self.to.rank - i,
+                Color::Black => self.to.rank + i,
+            };
+        }
+        squares
+    }
+
+    pub fn choice_at(&self, square: Square) -> Option<Type> {
+        self.squares()
+            .iter()
+            .position(|&s| s == square)
+            .map(|i| CHOICES[i])
+    }
+
+    /// the choice selected with the keyboard
+    pub fn highlighted(&self) -> Type {
+        CHOICES[self.highlighted]
+    }
+
+    pub fn highlighted_square(&self) -> Square {
+        self.squares()[self.highlighted]
+    }
+
+    pub fn highlight_next(&mut self) {
+        self.highlighted = (self.highlighted + 1) % CHOICES.len();
+    }
+
+    pub fn highlight_previous(&mut self) {
+        self.highlighted = (self.highlighted + CHOICES.len() - 1) % CHOICES.len();
+    }
+}
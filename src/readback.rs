@@ -0,0 +1,94 @@
+use std::{
+    future::Future,
+    ptr,
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+};
+
+use image::RgbaImage;
+
+/// copies `texture` back from the gpu into an image, swapping the channels of bgra formats
+/// so it's always rgba
+pub fn read_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    format: wgpu::TextureFormat,
+    (width, height): (u32, u32),
+) -> Result<RgbaImage, wgpu::BufferAsyncError> {
+    // rows of a texture copy must be padded to a multiple of 256 bytes
+    let unpadded_row = 4 * width;
+    let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let padded_row = unpadded_row + (alignment - unpadded_row % alignment) % alignment;
+
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback Buffer"),
+        size: (padded_row * height) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsage::MAP_READ | wgpu::BufferUsage::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Readback Encoder"),
+    });
+    encoder.copy_texture_to_buffer(
+        wgpu::TextureCopyView {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+        },
+        wgpu::BufferCopyView {
+            buffer: &buffer,
+            layout: wgpu::TextureDataLayout {
+                offset: 0,
+                bytes_per_row: padded_row,
+                rows_per_image: height,
+            },
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth: 1,
+        },
+    );
+    queue.submit(Some(encoder.finish()));
+
+    let slice = buffer.slice(..);
+    wait(device, slice.map_async(wgpu::MapMode::Read))?;
+
+    let mut pixels = Vec::with_capacity((unpadded_row * height) as usize);
+    for row in slice.get_mapped_range().chunks(padded_row as usize) {
+        pixels.extend_from_slice(&row[..unpadded_row as usize]);
+    }
+    buffer.unmap();
+
+    if let wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb = format {
+        for pixel in pixels.chunks_mut(4) {
+            pixel.swap(0, 2);
+        }
+    }
+    Ok(RgbaImage::from_raw(width, height, pixels).expect("the rows were unpadded"))
+}
+
+/// blocks on a mapping by polling the device until it's done, so screenshots can be taken
+/// from the event loop without an executor
+fn wait<F: Future>(device: &wgpu::Device, future: F) -> F::Output {
+    let waker = noop_waker();
+    let mut context = Context::from_waker(&waker);
+    let mut future = Box::pin(future);
+    loop {
+        device.poll(wgpu::Maintain::Wait);
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+    }
+}
+
+/// the mapping is driven by `Device::poll` rather than woken up, so the waker does nothing
+fn noop_waker() -> Waker {
+    fn clone(_: *const ()) -> RawWaker {
+        RawWaker::new(ptr::null(), &VTABLE)
+    }
+    fn noop(_: *const ()) {}
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+    unsafe { Waker::from_raw(RawWaker::new(ptr::null(), &VTABLE)) }
+}
@@ -1,11 +1,34 @@
-use crate::Board;
+use crate::board::{Board, Orientation};
 
+/// the area of the target the board is drawn in, in physical pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// everything a layer needs to build its pipelines and buffers, given when the layer is
+/// created and again whenever the target is resized
+pub struct LayerContext<'a> {
+    pub device: &'a wgpu::Device,
+    pub queue: &'a wgpu::Queue,
+    /// the color format of the render pass the layer draws into
+    pub format: wgpu::TextureFormat,
+    /// the multisample count pipelines must be created with
+    pub sample_count: u32,
+    pub viewport: Viewport,
+}
+
+/// a layer of the board, every layer draws into the same render pass in turn
 pub trait Renderable {
-    fn render(
-        &self,
-        encoder: &mut wgpu::CommandEncoder,
-        queue: &mut wgpu::Queue,
-        frame: &wgpu::SwapChainTexture,
-        board: &Board,
-    );
+    /// writes the layer's buffers for the next frame, before the render pass begins
+    fn prepare(&mut self, queue: &wgpu::Queue, board: &Board, orientation: Orientation);
+
+    /// records the layer's draw calls into the shared render pass
+    fn render<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>);
+
+    /// called after the target was resized
+    fn resize(&mut self, _context: &LayerContext) {}
 }
@@ -0,0 +1,704 @@
+use std::{iter, sync::Arc, time::Duration};
+
+use image::RgbaImage;
+use nalgebra::{Matrix4, Vector3};
+use winit::dpi::PhysicalPosition;
+
+use crate::{
+    annotation::Annotation,
+    annotations::AnnotationsView,
+    background::Background,
+    blit::{Blit, Offscreen},
+    board::{Board, Orientation, Square},
+    coordinates::CoordinatesView,
+    error::Error,
+    layers::{LayerId, LayerStack},
+    markers::MarkersView,
+    overlay::OverlayView,
+    piece_set::{PieceSet, SvgPieceSet},
+    pieces::PiecesView,
+    promotion::PromotionChooser,
+    readback::read_texture,
+    renderable::{LayerContext, Renderable, Viewport},
+    theme::{Highlight, Theme},
+};
+
+/// how much bigger a piece is drawn while being dragged
+const DRAG_SCALE: f32 = 1.2;
+
+/// where the layers draw, either straight into the target or into a multisampled texture
+/// that's resolved into it
+pub(crate) struct RenderTarget<'a> {
+    pub view: &'a wgpu::TextureView,
+    pub resolve_target: Option<&'a wgpu::TextureView>,
+}
+
+impl<'a> RenderTarget<'a> {
+    fn color_attachment(
+        &self,
+        load: wgpu::LoadOp<wgpu::Color>,
+    ) -> wgpu::RenderPassColorAttachmentDescriptor<'a> {
+        wgpu::RenderPassColorAttachmentDescriptor {
+            attachment: self.view,
+            resolve_target: self.resolve_target,
+            ops: wgpu::Operations { load, store: true },
+        }
+    }
+}
+
+/// the settings shared by every way of drawing the board, so the same code can set up
+/// `BoardRenderer` and `SoftwareRenderer`
+pub trait Renderer {
+    fn theme(&self) -> &Theme;
+
+    /// switches the board colors, takes effect on the next render
+    fn set_theme(&mut self, theme: Theme);
+
+    /// highlights `squares` with the theme's color for `kind`, replacing the squares
+    /// previously highlighted with it
+    fn set_highlight(&mut self, kind: Highlight, squares: &[Square]);
+
+    fn orientation(&self) -> Orientation;
+
+    fn set_orientation(&mut self, orientation: Orientation);
+
+    /// whether the files and ranks are drawn along the edges of the board, which every
+    /// renderer does by default
+    fn set_coordinates(&mut self, coordinates: bool);
+
+    /// replaces the arrows and circles drawn over the pieces
+    fn set_annotations(&mut self, annotations: &[Annotation]);
+}
+
+/// the renderers that draw pieces from images, rather than as text
+pub trait PieceSetRenderer: Renderer {
+    /// replaces the piece images, for instance with a set loaded by `PieceSet::load_dir`
+    fn set_piece_set(&mut self, set: &PieceSet);
+}
+
+/// draws the board with a device and queue owned by someone else, into any texture view of
+/// the format it was created for, for example as a widget inside another ui
+pub struct BoardRenderer {
+    device: Arc<wgpu::Device>,
+    queue: Arc<wgpu::Queue>,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    /// where the board was last drawn in its target
+    viewport: Viewport,
+    background: Background,
+    coordinates_view: CoordinatesView,
+    markers_view: MarkersView,
+    pieces_view: PiecesView,
+    annotations_view: AnnotationsView,
+    overlay_view: OverlayView,
+    layers: LayerStack,
+    svg_pieces: Option<SvgPieceSet>,
+    /// only created once the board is multisampled into a view it doesn't own
+    blit: Option<(Blit, Offscreen)>,
+    last_board: Option<Board>,
+    /// the square whose piece shouldn't slide into place on the next board change
+    skipped_tween: Option<Square>,
+    animation_duration: Duration,
+    orientation: Orientation,
+}
+
+impl BoardRenderer {
+    /// creates pipelines for views of `format` with `sample_count` samples per pixel, 1
+    /// disables msaa and 4 is the only other count allowed
+    pub fn new(
+        device: Arc<wgpu::Device>,
+        queue: Arc<wgpu::Queue>,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Result<BoardRenderer, Error> {
+        if !is_supported_format(format) {
+            return Err(Error::UnsupportedFormat(format));
+        }
+        if sample_count != 1 && sample_count != 4 {
+            return Err(Error::UnsupportedSampleCount(sample_count));
+        }
+
+        let background = Background::new(&device, format, sample_count);
+
+        let coordinates_view = CoordinatesView::new(&device, format, sample_count);
+
+        let markers_view = MarkersView::new(&device, format, sample_count);
+
+        let pieces_view = PiecesView::new(&device, &queue, format, sample_count)?;
+
+        let annotations_view = AnnotationsView::new(&device, format, sample_count);
+
+        let overlay_view = OverlayView::new(&device, format, sample_count);
+
+        Ok(BoardRenderer {
+            device,
+            queue,
+            format,
+            sample_count,
+            viewport: Viewport {
+                x: 0.0,
+                y: 0.0,
+                width: 1.0,
+                height: 1.0,
+            },
+            background,
+            coordinates_view,
+            markers_view,
+            pieces_view,
+            annotations_view,
+            overlay_view,
+            layers: LayerStack::default(),
+            svg_pieces: None,
+            blit: None,
+            last_board: None,
+            skipped_tween: None,
+            animation_duration: Duration::from_millis(200),
+            orientation: Orientation::White,
+        })
+    }
+
+    pub fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    pub fn queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// marks the squares the selected piece can move to, `captures` get a ring and the
+    /// rest of `targets` a dot
+    pub fn set_move_targets(&mut self, targets: &[Square], captures: &[Square]) {
+        self.markers_view.set_targets(targets, captures);
+    }
+
+    pub fn clear_move_targets(&mut self) {
+        self.markers_view.set_targets(&[], &[]);
+    }
+
+    pub fn annotations(&self) -> &[Annotation] {
+        self.annotations_view.annotations()
+    }
+
+    /// draws an arrow or circle over the pieces, returns false if it's already shown
+    pub fn add_annotation(&mut self, annotation: Annotation) -> bool {
+        self.annotations_view.add(annotation)
+    }
+
+    /// returns false if the annotation wasn't shown
+    pub fn remove_annotation(&mut self, annotation: &Annotation) -> bool {
+        self.annotations_view.remove(annotation)
+    }
+
+    pub fn clear_annotations(&mut self) {
+        self.annotations_view.clear();
+    }
+
+    pub fn theme(&self) -> &Theme {
+        self.background.theme()
+    }
+
+    /// switches the board colors, takes effect on the next render
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.background.set_theme(theme);
+        self.coordinates_view.set_theme(theme);
+    }
+
+    /// highlights `squares` with the theme's color for `kind`, replacing the squares
+    /// previously highlighted with it
+    pub fn set_highlight(&mut self, kind: Highlight, squares: &[Square]) {
+        self.background.highlights_mut().set(kind, squares);
+    }
+
+    /// whether the files and ranks are drawn along the edges of the board
+    pub fn coordinates(&self) -> bool {
+        self.is_layer_visible(LayerId::COORDINATES)
+    }
+
+    /// shows or hides the coordinates, which is the same as toggling `LayerId::COORDINATES`
+    pub fn set_coordinates(&mut self, coordinates: bool) {
+        self.set_layer_visible(LayerId::COORDINATES, coordinates);
+    }
+
+    /// replaces the piece images, for instance with a set loaded by `PieceSet::load_dir`
+    pub fn set_piece_set(&mut self, set: &PieceSet) {
+        self.svg_pieces = None;
+        self.pieces_view
+            .set_piece_set(&self.device, &self.queue, set);
+    }
+
+    /// replaces the piece images with svgs, which are rasterized again whenever the board
+    /// is resized so they're always drawn at their native resolution
+    pub fn set_svg_piece_set(&mut self, set: SvgPieceSet) {
+        self.svg_pieces = Some(set);
+        self.rasterize_pieces();
+    }
+
+    fn rasterize_pieces(&mut self) {
+        if let Some(svg_pieces) = &self.svg_pieces {
+            let square_size = self.viewport.width.min(self.viewport.height) as u32 / 8;
+            let set = svg_pieces.rasterize(square_size);
+            self.pieces_view
+                .set_piece_set(&self.device, &self.queue, &set);
+        }
+    }
+
+    /// the layers from bottom to top, including hidden ones
+    pub fn layers(&self) -> Vec<LayerId> {
+        self.layers.ids()
+    }
+
+    /// adds the layer built by `create` at `index` in the stack, counted from the bottom
+    pub fn insert_layer<L, F>(&mut self, index: usize, create: F) -> LayerId
+    where
+        L: Renderable + 'static,
+        F: FnOnce(&LayerContext) -> L,
+    {
+        let layer = create(&self.layer_context());
+        self.layers.insert(index, Box::new(layer))
+    }
+
+    /// adds the layer built by `create` above all the others
+    pub fn push_layer<L, F>(&mut self, create: F) -> LayerId
+    where
+        L: Renderable + 'static,
+        F: FnOnce(&LayerContext) -> L,
+    {
+        self.insert_layer(usize::MAX, create)
+    }
+
+    /// removes a layer added with `insert_layer`, returns false if there's no such layer or
+    /// it's builtin, hide those with `set_layer_visible` instead
+    pub fn remove_layer(&mut self, id: LayerId) -> bool {
+        self.layers.remove(id)
+    }
+
+    /// moves the layer to `index` in the stack, returns false if there's no such layer
+    pub fn move_layer(&mut self, id: LayerId, index: usize) -> bool {
+        self.layers.move_to(id, index)
+    }
+
+    pub fn is_layer_visible(&self, id: LayerId) -> bool {
+        self.layers.is_visible(id)
+    }
+
+    /// hidden layers are neither prepared nor drawn, returns false if there's no such layer
+    pub fn set_layer_visible(&mut self, id: LayerId, visible: bool) -> bool {
+        self.layers.set_visible(id, visible)
+    }
+
+    /// a layer added with `insert_layer`, if it has the type `L`
+    pub fn layer<L: Renderable + 'static>(&self, id: LayerId) -> Option<&L> {
+        self.layers.custom(id)?.as_any().downcast_ref()
+    }
+
+    pub fn layer_mut<L: Renderable + 'static>(&mut self, id: LayerId) -> Option<&mut L> {
+        self.layers.custom_mut(id)?.as_any_mut().downcast_mut()
+    }
+
+    fn layer_context(&self) -> LayerContext<'_> {
+        LayerContext {
+            device: &self.device,
+            queue: &self.queue,
+            format: self.format,
+            sample_count: self.sample_count,
+            viewport: self.viewport,
+        }
+    }
+
+    /// shows the promotion picker over the board, `None` hides it
+    pub fn set_promotion_chooser(&mut self, promotion: Option<PromotionChooser>) {
+        self.overlay_view.set_promotion(promotion);
+    }
+
+    /// how long pieces take to slide to a new square, zero disables animation
+    pub fn set_animation_duration(&mut self, duration: Duration) {
+        self.animation_duration = duration;
+    }
+
+    /// whether a move is still being animated, keep redrawing until it's done
+    pub fn is_animating(&self) -> bool {
+        self.pieces_view.is_animating()
+    }
+
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
+    pub fn set_orientation(&mut self, orientation: Orientation) {
+        self.orientation = orientation;
+    }
+
+    pub fn viewport(&self) -> Viewport {
+        self.viewport
+    }
+
+    /// moves the board within its target, this is done by `render` so it only needs to be
+    /// called to hit test before the first frame
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        let resized =
+            viewport.width != self.viewport.width || viewport.height != self.viewport.height;
+        self.viewport = viewport;
+        if resized {
+            self.rasterize_pieces();
+            let context = LayerContext {
+                device: &self.device,
+                queue: &self.queue,
+                format: self.format,
+                sample_count: self.sample_count,
+                viewport,
+            };
+            self.coordinates_view.resize(&context);
+            for layer in self.layers.iter_mut() {
+                if let Some(custom) = &mut layer.custom {
+                    custom.resize(&context);
+                }
+            }
+        }
+    }
+
+    /// the square under `position` (in the target's coordinates)
+    pub fn square_at(&self, position: PhysicalPosition<f64>) -> Option<Square> {
+        let (x, y) = self.board_position(position);
+        if !(0.0..1.0).contains(&x) || !(0.0..1.0).contains(&y) {
+            return None;
+        }
+        let file = (x * 8.0) as usize;
+        let rank = 7 - (y * 8.0) as usize;
+        Some(Square::new(file, rank).oriented(self.orientation))
+    }
+
+    /// draws the piece on `from` under `position` (in the target's coordinates), slightly
+    /// enlarged and above everything else
+    pub fn drag_piece(&mut self, from: Square, position: PhysicalPosition<f64>) {
+        let (x, y) = self.board_position(position);
+        let transform = Matrix4::new_scaling(DRAG_SCALE / 8.0).append_translation(&Vector3::new(
+            (x * 2.0 - 1.0) as f32,
+            (1.0 - y * 2.0) as f32,
+            0.0,
+        ));
+        self.pieces_view.set_drag(from, transform);
+    }
+
+    /// stops drawing the dragged piece, it goes back to its square until the board changes
+    pub fn drop_piece(&mut self) {
+        self.pieces_view.clear_drag();
+    }
+
+    /// puts the piece arriving on `square` straight onto it when the board next changes,
+    /// for instance after it was dropped there. the rest of the move, like a castling rook
+    /// or a captured pawn, is still animated
+    pub fn skip_animation_for(&mut self, square: Square) {
+        self.skipped_tween = Some(square);
+    }
+
+    /// converts target coordinates to fractions of the board from its top left corner
+    fn board_position(&self, position: PhysicalPosition<f64>) -> (f64, f64) {
+        let viewport = self.viewport;
+        (
+            (position.x - viewport.x as f64) / viewport.width as f64,
+            (position.y - viewport.y as f64) / viewport.height as f64,
+        )
+    }
+
+    /// draws `board` over `viewport` of `view`, leaving the rest of it as it was
+    pub fn render(&mut self, board: &Board, view: &wgpu::TextureView, viewport: Viewport) {
+        self.set_viewport(viewport);
+        self.prepare(board);
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Board Render Encoder"),
+            });
+
+        if self.sample_count > 1 {
+            // resolving would overwrite all of `view`, so the board is resolved into a texture
+            // of its own which is then drawn over the viewport
+            let size = (
+                (viewport.width.ceil() as u32).max(1),
+                (viewport.height.ceil() as u32).max(1),
+            );
+            if !matches!(&self.blit, Some((_, offscreen)) if offscreen.size == size) {
+                let blit = match self.blit.take() {
+                    Some((blit, _)) => blit,
+                    None => Blit::new(&self.device, self.format),
+                };
+                let offscreen =
+                    blit.create_offscreen(&self.device, self.format, self.sample_count, size);
+                self.blit = Some((blit, offscreen));
+            }
+            let (blit, offscreen) = self.blit.as_ref().unwrap();
+            let target = RenderTarget {
+                view: &offscreen.multisampled,
+                resolve_target: Some(&offscreen.resolved),
+            };
+            self.draw(
+                &mut encoder,
+                &target,
+                wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                None,
+            );
+
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Board Blit Render Pass"),
+                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment: view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+            });
+            set_viewport(&mut render_pass, viewport);
+            blit.render(&mut render_pass, offscreen);
+        } else {
+            let target = RenderTarget {
+                view,
+                resolve_target: None,
+            };
+            self.draw(&mut encoder, &target, wgpu::LoadOp::Load, Some(viewport));
+        }
+
+        self.queue.submit(iter::once(encoder.finish()));
+    }
+
+    /// draws `board` into a new `size`x`size` image and reads it back, for screenshots. srgb
+    /// formats give srgb images
+    pub fn render_to_image(
+        &mut self,
+        board: &Board,
+        size: u32,
+    ) -> Result<RgbaImage, wgpu::BufferAsyncError> {
+        let size = size.max(1);
+        let previous_viewport = self.viewport;
+        self.set_viewport(Viewport {
+            x: 0.0,
+            y: 0.0,
+            width: size as f32,
+            height: size as f32,
+        });
+        self.prepare(board);
+
+        let create_texture = |sample_count, usage, label| {
+            self.device.create_texture(&wgpu::TextureDescriptor {
+                size: wgpu::Extent3d {
+                    width: size,
+                    height: size,
+                    depth: 1,
+                },
+                mip_level_count: 1,
+                sample_count,
+                dimension: wgpu::TextureDimension::D2,
+                format: self.format,
+                usage,
+                label: Some(label),
+            })
+        };
+        let texture = create_texture(
+            1,
+            wgpu::TextureUsage::RENDER_ATTACHMENT | wgpu::TextureUsage::COPY_SRC,
+            "Board Image Texture",
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let multisampled = if self.sample_count > 1 {
+            let texture = create_texture(
+                self.sample_count,
+                wgpu::TextureUsage::RENDER_ATTACHMENT,
+                "Board Image Multisampled Texture",
+            );
+            Some(texture.create_view(&wgpu::TextureViewDescriptor::default()))
+        } else {
+            None
+        };
+        let target = match &multisampled {
+            Some(multisampled) => RenderTarget {
+                view: multisampled,
+                resolve_target: Some(&view),
+            },
+            None => RenderTarget {
+                view: &view,
+                resolve_target: None,
+            },
+        };
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Board Image Encoder"),
+            });
+        self.draw(
+            &mut encoder,
+            &target,
+            wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+            None,
+        );
+        self.queue.submit(iter::once(encoder.finish()));
+
+        self.set_viewport(previous_viewport);
+        read_texture(
+            &self.device,
+            &self.queue,
+            &texture,
+            self.format,
+            (size, size),
+        )
+    }
+
+    /// starts animating if the board changed and writes every layer's buffers
+    pub(crate) fn prepare(&mut self, board: &Board) {
+        match self.last_board {
+            Some(last_board) if last_board != *board => {
+                self.pieces_view
+                    .animate(&last_board, board, self.animation_duration);
+                if let Some(square) = self.skipped_tween.take() {
+                    self.pieces_view.skip_tween(square);
+                }
+            }
+            _ => {}
+        }
+        self.last_board = Some(*board);
+
+        let orientation = self.orientation;
+        let layers = &self.layers;
+        let visible = |id| layers.is_visible(id);
+        if visible(LayerId::BACKGROUND) {
+            self.background.prepare(&self.queue, board, orientation);
+        }
+        if visible(LayerId::COORDINATES) {
+            self.coordinates_view
+                .prepare_texture(&self.device, &self.queue, orientation);
+        }
+        if visible(LayerId::MARKERS) {
+            self.markers_view.prepare(&self.queue, board, orientation);
+        }
+        // the dragged piece is written along with the others
+        if visible(LayerId::PIECES) || visible(LayerId::DRAGGED_PIECE) {
+            self.pieces_view.prepare(&self.queue, board, orientation);
+        }
+        if visible(LayerId::ANNOTATIONS) {
+            self.annotations_view
+                .prepare(&self.queue, board, orientation);
+        }
+        if visible(LayerId::OVERLAY) {
+            self.overlay_view.prepare(&self.queue, board, orientation);
+        }
+        if visible(LayerId::OVERLAY_PIECES) {
+            let overlay_pieces = self.overlay_view.pieces(orientation);
+            self.pieces_view
+                .prepare_overlay(&self.queue, &overlay_pieces);
+        }
+        for layer in self.layers.iter_mut() {
+            match &mut layer.custom {
+                Some(custom) if layer.visible => custom.prepare(&self.queue, board, orientation),
+                _ => {}
+            }
+        }
+    }
+
+    /// records every visible layer into a single pass over `target`, limited to `viewport`
+    /// if there is one
+    pub(crate) fn draw(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &RenderTarget,
+        load: wgpu::LoadOp<wgpu::Color>,
+        viewport: Option<Viewport>,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Render Pass"),
+            color_attachments: &[target.color_attachment(load)],
+            depth_stencil_attachment: None,
+        });
+        if let Some(viewport) = viewport {
+            set_viewport(&mut render_pass, viewport);
+        }
+
+        for layer in self.layers.iter().filter(|layer| layer.visible) {
+            match (&layer.custom, layer.id) {
+                (Some(custom), _) => custom.render(&mut render_pass),
+                (None, LayerId::BACKGROUND) => self.background.render(&mut render_pass),
+                (None, LayerId::COORDINATES) => self.coordinates_view.render(&mut render_pass),
+                (None, LayerId::MARKERS) => self.markers_view.render(&mut render_pass),
+                (None, LayerId::PIECES) => self.pieces_view.render(&mut render_pass),
+                (None, LayerId::ANNOTATIONS) => self.annotations_view.render(&mut render_pass),
+                (None, LayerId::DRAGGED_PIECE) => self.pieces_view.render_drag(&mut render_pass),
+                (None, LayerId::OVERLAY) => self.overlay_view.render(&mut render_pass),
+                (None, LayerId::OVERLAY_PIECES) => {
+                    self.pieces_view.render_overlay(&mut render_pass)
+                }
+                (None, _) => {}
+            }
+        }
+    }
+}
+
+impl Renderer for BoardRenderer {
+    fn theme(&self) -> &Theme {
+        BoardRenderer::theme(self)
+    }
+
+    fn set_theme(&mut self, theme: Theme) {
+        BoardRenderer::set_theme(self, theme);
+    }
+
+    fn set_highlight(&mut self, kind: Highlight, squares: &[Square]) {
+        BoardRenderer::set_highlight(self, kind, squares);
+    }
+
+    fn orientation(&self) -> Orientation {
+        BoardRenderer::orientation(self)
+    }
+
+    fn set_orientation(&mut self, orientation: Orientation) {
+        BoardRenderer::set_orientation(self, orientation);
+    }
+
+    fn set_coordinates(&mut self, coordinates: bool) {
+        BoardRenderer::set_coordinates(self, coordinates);
+    }
+
+    fn set_annotations(&mut self, annotations: &[Annotation]) {
+        self.clear_annotations();
+        for &annotation in annotations {
+            self.add_annotation(annotation);
+        }
+    }
+}
+
+impl PieceSetRenderer for BoardRenderer {
+    fn set_piece_set(&mut self, set: &PieceSet) {
+        BoardRenderer::set_piece_set(self, set);
+    }
+}
+
+/// the formats the layers' pipelines can blend into
+fn is_supported_format(format: wgpu::TextureFormat) -> bool {
+    matches!(
+        format,
+        wgpu::TextureFormat::Bgra8UnormSrgb
+            | wgpu::TextureFormat::Bgra8Unorm
+            | wgpu::TextureFormat::Rgba8UnormSrgb
+            | wgpu::TextureFormat::Rgba8Unorm
+    )
+}
+
+fn set_viewport(render_pass: &mut wgpu::RenderPass, viewport: Viewport) {
+    render_pass.set_viewport(
+        viewport.x,
+        viewport.y,
+        viewport.width,
+        viewport.height,
+        0.0,
+        1.0,
+    );
+}
@@ -0,0 +1,810 @@
+use thiserror::Error;
+
+use crate::{
+    board::{default_board, Board, Square},
+    piece::{Color, Piece, Type},
+    selection::MoveValidator,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Move {
+    pub from: Square,
+    pub to: Square,
+    /// the piece a pawn turns into on the last rank
+    pub promotion: Option<Type>,
+}
+
+impl Move {
+    pub const fn new(from: Square, to: Square) -> Move {
+        Move {
+            from,
+            to,
+            promotion: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CastlingRights {
+    pub white_king_side: bool,
+    pub white_queen_side: bool,
+    pub black_king_side: bool,
+    pub black_queen_side: bool,
+}
+
+impl CastlingRights {
+    pub const ALL: CastlingRights = CastlingRights {
+        white_king_side: true,
+        white_queen_side: true,
+        black_king_side: true,
+        black_queen_side: true,
+    };
+
+    pub const NONE: CastlingRights = CastlingRights {
+        white_king_side: false,
+        white_queen_side: false,
+        black_king_side: false,
+        black_queen_side: false,
+    };
+
+    fn get(&self, color: Color, king_side: bool) -> bool {
+        match (color, king_side) {
+            (Color::White, true) => self.white_king_side,
+            (Color::White, false) => self.white_queen_side,
+            (Color::Black, true) => self.black_king_side,
+            (Color::Black, false) => self.black_queen_side,
+        }
+    }
+
+    /// drops any rights that depend on the king or a rook standing on `square`
+    fn touch(&mut self, square: Square) {
+        match (square.file, square.rank) {
+            (4, 0) => {
+                self.white_king_side = false;
+                self.white_queen_side = false;
+            }
+            (4, 7) => {
+                self.black_king_side = false;
+                self.black_queen_side = false;
+            }
+            (7, 0) => self.white_king_side = false,
+            (0, 0) => self.white_queen_side = false,
+            (7, 7) => self.black_king_side = false,
+            (0, 7) => self.black_queen_side = false,
+            _ => {}
+        }
+    }
+}
+
+/// a board along with everything else needed to know which moves are legal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub board: Board,
+    pub turn: Color,
+    pub castling: CastlingRights,
+    /// the square a pawn skipped over with a double step on the previous move
+    pub en_passant: Option<Square>,
+    pub halfmove_clock: u32,
+    pub fullmove_number: u32,
+}
+
+impl Default for Position {
+    fn default() -> Position {
+        Position {
+            board: default_board(),
+            turn: Color::White,
+            castling: CastlingRights::ALL,
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum FenError {
+    #[error("expected at least 4 fields but found {0}")]
+    Fields(usize),
+    #[error("invalid piece placement {0:?}")]
+    Placement(String),
+    #[error("invalid side to move {0:?}")]
+    Turn(String),
+    #[error("invalid castling rights {0:?}")]
+    Castling(String),
+    #[error("invalid en passant square {0:?}")]
+    EnPassant(String),
+    #[error("invalid move counter {0:?}")]
+    Counter(String),
+}
+
+const KNIGHT_OFFSETS: [(isize, isize); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+const KING_OFFSETS: [(isize, isize); 8] = [
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+];
+const ROOK_DIRECTIONS: [(isize, isize); 4] = [(0, 1), (1, 0), (0, -1), (-1, 0)];
+const BISHOP_DIRECTIONS: [(isize, isize); 4] = [(1, 1), (1, -1), (-1, -1), (-1, 1)];
+const PROMOTIONS: [Type; 4] = [Type::Queen, Type::Rook, Type::Bishop, Type::Knight];
+
+fn offset(square: Square, (file, rank): (isize, isize)) -> Option<Square> {
+    let file = square.file as isize + file;
+    let rank = square.rank as isize + rank;
+    if (0..8).contains(&file) && (0..8).contains(&rank) {
+        Some(Square::new(file as usize, rank as usize))
+    } else {
+        None
+    }
+}
+
+fn forward(color: Color) -> isize {
+    match color {
+        Color::White => 1,
+        Color::Black => -1,
+    }
+}
+
+fn back_rank(color: Color) -> usize {
+    match color {
+        Color::White => 0,
+        Color::Black => 7,
+    }
+}
+
+impl Position {
+    /// parses forsyth-edwards notation, the move counters may be left out
+    pub fn from_fen(fen: &str) -> Result<Position, FenError> {
+        let fields: Vec<_> = fen.split_whitespace().collect();
+        if !(4..=6).contains(&fields.len()) {
+            return Err(FenError::Fields(fields.len()));
+        }
+
+        let placement_error = || FenError::Placement(fields[0].to_owned());
+        let ranks: Vec<_> = fields[0].split('/').collect();
+        if ranks.len() != 8 {
+            return Err(placement_error());
+        }
+        let mut board = [[None; 8]; 8];
+        for (row, rank) in ranks.iter().enumerate() {
+            let mut file = 0;
+            for c in rank.chars() {
+                if let Some(empty) = c.to_digit(10) {
+                    if empty == 0 {
+                        return Err(placement_error());
+                    }
+                    file += empty as usize;
+                    continue;
+                }
+                let type_ = match c.to_ascii_lowercase() {
+                    'k' => Type::King,
+                    'q' => Type::Queen,
+                    'r' => Type::Rook,
+                    'b' => Type::Bishop,
+                    'n' => Type::Knight,
+                    'p' => Type::Pawn,
+                    _ => return Err(placement_error()),
+                };
+                let color = if c.is_ascii_uppercase() {
+                    Color::White
+                } else {
+                    Color::Black
+                };
+                if file >= 8 {
+                    return Err(placement_error());
+                }
+                board[row][file] = Some(Piece::new(color, type_));
+                file += 1;
+            }
+            if file != 8 {
+                return Err(placement_error());
+            }
+        }
+
+        let turn = match fields[1] {
+            "w" => Color::White,
+            "b" => Color::Black,
+            turn => return Err(FenError::Turn(turn.to_owned())),
+        };
+
+        let mut castling = CastlingRights::NONE;
+        if fields[2] != "-" {
+            for c in fields[2].chars() {
+                match c {
+                    'K' => castling.white_king_side = true,
+                    'Q' => castling.white_queen_side = true,
+                    'k' => castling.black_king_side = true,
+                    'q' => castling.black_queen_side = true,
+                    _ => return Err(FenError::Castling(fields[2].to_owned())),
+                }
+            }
+        }
+
+        let en_passant = match fields[3] {
+            "-" => None,
+            square => Some(
+                Square::from_name(square).ok_or_else(|| FenError::EnPassant(square.to_owned()))?,
+            ),
+        };
+
+        let counter = |i: usize, default: u32| match fields.get(i) {
+            Some(field) => field
+                .parse()
+                .map_err(|_| FenError::Counter((*field).to_owned())),
+            None => Ok(default),
+        };
+
+        Ok(Position {
+            board,
+            turn,
+            castling,
+            en_passant,
+            halfmove_clock: counter(4, 0)?,
+            fullmove_number: counter(5, 1)?,
+        })
+    }
+
+    pub fn piece(&self, square: Square) -> Option<Piece> {
+        square.piece(&self.board)
+    }
+
+    /// whether any piece of `by` attacks `square`
+    pub fn is_attacked(&self, square: Square, by: Color) -> bool {
+        let is = |target: Option<Square>, types: &[Type]| match target.and_then(|t| self.piece(t)) {
+            Some(piece) => piece.color == by && types.contains(&piece.type_),
+            None => false,
+        };
+        let pawn_rank = -forward(by);
+        if is(offset(square, (-1, pawn_rank)), &[Type::Pawn])
+            || is(offset(square, (1, pawn_rank)), &[Type::Pawn])
+        {
+            return true;
+        }
+        if KNIGHT_OFFSETS
+            .iter()
+            .any(|&o| is(offset(square, o), &[Type::Knight]))
+            || KING_OFFSETS
+                .iter()
+                .any(|&o| is(offset(square, o), &[Type::King]))
+        {
+            return true;
+        }
+        let slides = |directions: &[(isize, isize)], types: &[Type]| {
+            directions.iter().any(|&direction| {
+                let mut current = square;
+                while let Some(next) = offset(current, direction) {
+                    if self.piece(next).is_some() {
+                        return is(Some(next), types);
+                    }
+                    current = next;
+                }
+                false
+            })
+        };
+        slides(&ROOK_DIRECTIONS, &[Type::Rook, Type::Queen])
+            || slides(&BISHOP_DIRECTIONS, &[Type::Bishop, Type::Queen])
+    }
+
+    pub fn king(&self, color: Color) -> Option<Square> {
+        (0..64)
+            .map(|i| Square::new(i % 8, i / 8))
+            .find(|&square| self.piece(square) == Some(Piece::new(color, Type::King)))
+    }
+
+    pub fn is_check(&self) -> bool {
+        match self.king(self.turn) {
+            Some(king) => self.is_attacked(king, self.turn.opposite()),
+            None => false,
+        }
+    }
+
+    pub fn is_checkmate(&self) -> bool {
+        self.is_check() && self.legal_moves().is_empty()
+    }
+
+    pub fn is_stalemate(&self) -> bool {
+        !self.is_check() && self.legal_moves().is_empty()
+    }
+
+    /// all legal moves for the side to move
+    pub fn legal_moves(&self) -> Vec<Move> {
+        (0..64)
+            .map(|i| Square::new(i % 8, i / 8))
+            .flat_map(|square| self.legal_moves_from(square))
+            .collect()
+    }
+
+    /// legal moves of the piece on `from`, empty if it isn't the piece's turn
+    pub fn legal_moves_from(&self, from: Square) -> Vec<Move> {
+        let mut moves = self.pseudo_legal_moves_from(from);
+        moves.retain(|&mv| {
+            let mut after = *self;
+            after.play_unchecked(mv);
+            match after.king(self.turn) {
+                Some(king) => !after.is_attacked(king, self.turn.opposite()),
+                None => true,
+            }
+        });
+        moves
+    }
+
+    /// whether `mv` takes a piece, which for en passant isn't on the square moved to
+    pub fn is_capture(&self, mv: Move) -> bool {
+        match self.piece(mv.from) {
+            Some(piece) if piece.type_ == Type::Pawn && self.en_passant == Some(mv.to) => true,
+            Some(piece) => matches!(self.piece(mv.to), Some(target) if target.color != piece.color),
+            None => false,
+        }
+    }
+
+    pub fn is_legal(&self, mv: Move) -> bool {
+        self.legal_moves_from(mv.from).contains(&mv)
+    }
+
+    /// plays `mv` if it's legal, returns whether it was played
+    pub fn play(&mut self, mv: Move) -> bool {
+        if !self.is_legal(mv) {
+            return false;
+        }
+        self.play_unchecked(mv);
+        true
+    }
+
+    fn pseudo_legal_moves_from(&self, from: Square) -> Vec<Move> {
+        let piece = match self.piece(from) {
+            Some(piece) if piece.color == self.turn => piece,
+            _ => return Vec::new(),
+        };
+        let mut targets = Vec::new();
+        let is_free = |square: Square| self.piece(square).is_none();
+        let is_enemy =
+            |square: Square| matches!(self.piece(square), Some(p) if p.color != piece.color);
+        match piece.type_ {
+            Type::Pawn => {
+                let dir = forward(piece.color);
+                if let Some(one) = offset(from, (0, dir)).filter(|&s| is_free(s)) {
+                    targets.push(one);
+                    let start_rank = (back_rank(piece.color) as isize + dir) as usize;
+                    if from.rank == start_rank {
+                        targets.extend(offset(one, (0, dir)).filter(|&s| is_free(s)));
+                    }
+                }
+                for side in &[-1, 1] {
+                    if let Some(capture) = offset(from, (*side, dir)) {
+                        if is_enemy(capture) || self.en_passant == Some(capture) {
+                            targets.push(capture);
+                        }
+                    }
+                }
+            }
+            Type::Knight | Type::King => {
+                let offsets = if piece.type_ == Type::Knight {
+                    &KNIGHT_OFFSETS
+                } else {
+                    &KING_OFFSETS
+                };
+                targets.extend(
+                    offsets
+                        .iter()
+                        .filter_map(|&o| offset(from, o))
+                        .filter(|&s| is_free(s) || is_enemy(s)),
+                );
+                if piece.type_ == Type::King {
+                    targets.extend(self.castling_targets(from, piece.color));
+                }
+            }
+            Type::Bishop | Type::Rook | Type::Queen => {
+                let directions: Vec<_> = match piece.type_ {
+                    Type::Bishop => BISHOP_DIRECTIONS.to_vec(),
+                    Type::Rook => ROOK_DIRECTIONS.to_vec(),
+                    _ => [ROOK_DIRECTIONS, BISHOP_DIRECTIONS].concat(),
+                };
+                for direction in directions {
+                    let mut current = from;
+                    while let Some(next) = offset(current, direction) {
+                        if is_free(next) {
+                            targets.push(next);
+                        } else {
+                            if is_enemy(next) {
+                                targets.push(next);
+                            }
+                            break;
+                        }
+                        current = next;
+                    }
+                }
+            }
+        }
+
+        let promotion_rank = back_rank(piece.color.opposite());
+        let mut moves = Vec::new();
+        for to in targets {
+            if piece.type_ == Type::Pawn && to.rank == promotion_rank {
+                moves.extend(PROMOTIONS.iter().map(|&promotion| Move {
+                    from,
+                    to,
+                    promotion: Some(promotion),
+                }));
+            } else {
+                moves.push(Move::new(from, to));
+            }
+        }
+        moves
+    }
+
+    fn castling_targets(&self, from: Square, color: Color) -> Vec<Square> {
+        let rank = back_rank(color);
+        let enemy = color.opposite();
+        if from != Square::new(4, rank) || self.is_attacked(from, enemy) {
+            return Vec::new();
+        }
+        let mut targets = Vec::new();
+        for &(king_side, rook_file) in &[(true, 7), (false, 0)] {
+            if !self.castling.get(color, king_side)
+                || self.piece(Square::new(rook_file, rank)) != Some(Piece::new(color, Type::Rook))
+            {
+                continue;
+            }
+            let (between, passes, to): (&[usize], [usize; 2], usize) = if king_side {
+                (&[5, 6], [5, 6], 6)
+            } else {
+                (&[1, 2, 3], [3, 2], 2)
+            };
+            let is_clear = between
+                .iter()
+                .all(|&file| self.piece(Square::new(file, rank)).is_none());
+            let is_safe = passes
+                .iter()
+                .all(|&file| !self.is_attacked(Square::new(file, rank), enemy));
+            if is_clear && is_safe {
+                targets.push(Square::new(to, rank));
+            }
+        }
+        targets
+    }
+
+    fn play_unchecked(&mut self, mv: Move) {
+        let piece = match self.piece(mv.from) {
+            Some(piece) => piece,
+            None => return,
+        };
+        let is_capture = self.piece(mv.to).is_some();
+        let mut en_passant = None;
+
+        if piece.type_ == Type::Pawn {
+            if Some(mv.to) == self.en_passant && !is_capture {
+                Square::new(mv.to.file, mv.from.rank).set_piece(&mut self.board, None);
+            }
+            if (mv.to.rank as isize - mv.from.rank as isize).abs() == 2 {
+                en_passant = Some(Square::new(mv.from.file, (mv.from.rank + mv.to.rank) / 2));
+            }
+        }
+        if piece.type_ == Type::King && (mv.to.file as isize - mv.from.file as isize).abs() == 2 {
+            let (rook_from, rook_to) = if mv.to.file == 6 { (7, 5) } else { (0, 3) };
+            let rank = mv.from.rank;
+            let rook = Square::new(rook_from, rank).piece(&self.board);
+            Square::new(rook_from, rank).set_piece(&mut self.board, None);
+            Square::new(rook_to, rank).set_piece(&mut self.board, rook);
+        }
+
+        let placed = match (piece.type_, mv.promotion) {
+            (Type::Pawn, Some(promotion)) => Piece::new(piece.color, promotion),
+            _ => piece,
+        };
+        mv.from.set_piece(&mut self.board, None);
+        mv.to.set_piece(&mut self.board, Some(placed));
+
+        self.castling.touch(mv.from);
+        self.castling.touch(mv.to);
+        self.en_passant = en_passant;
+        if piece.type_ == Type::Pawn || is_capture {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        if self.turn == Color::Black {
+            self.fullmove_number += 1;
+        }
+        self.turn = self.turn.opposite();
+    }
+}
+
+impl MoveValidator for Position {
+    fn is_movable(&self, square: Square) -> bool {
+        matches!(self.piece(square), Some(piece) if piece.color == self.turn)
+    }
+
+    fn legal_targets(&self, from: Square) -> Vec<Square> {
+        let mut targets: Vec<_> = self.legal_moves_from(from).iter().map(|mv| mv.to).collect();
+        targets.dedup();
+        targets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(name: &str) -> Square {
+        Square::from_name(name).unwrap()
+    }
+
+    fn from_fen(fen: &str) -> Position {
+        Position::from_fen(fen).unwrap()
+    }
+
+    /// counts the leaf positions `depth` plies down
+    fn perft(position: &Position, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let moves = position.legal_moves();
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+        moves
+            .into_iter()
+            .map(|mv| {
+                let mut after = *position;
+                after.play_unchecked(mv);
+                perft(&after, depth - 1)
+            })
+            .sum()
+    }
+
+    fn targets(position: &Position, from: &str) -> Vec<Square> {
+        position
+            .legal_moves_from(square(from))
+            .iter()
+            .map(|mv| mv.to)
+            .collect()
+    }
+
+    #[test]
+    fn perft_start() {
+        let start = Position::default();
+        assert_eq!(perft(&start, 1), 20);
+        assert_eq!(perft(&start, 2), 400);
+        assert_eq!(perft(&start, 3), 8902);
+    }
+
+    #[test]
+    fn perft_kiwipete() {
+        let kiwipete =
+            from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        assert_eq!(perft(&kiwipete, 1), 48);
+        assert_eq!(perft(&kiwipete, 2), 2039);
+        assert_eq!(perft(&kiwipete, 3), 97862);
+    }
+
+    #[test]
+    fn perft_endgame() {
+        let endgame = from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1");
+        assert_eq!(perft(&endgame, 1), 14);
+        assert_eq!(perft(&endgame, 2), 191);
+        assert_eq!(perft(&endgame, 3), 2812);
+        assert_eq!(perft(&endgame, 4), 43238);
+    }
+
+    #[test]
+    fn perft_promotions() {
+        let promotions =
+            from_fen("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1");
+        assert_eq!(perft(&promotions, 1), 6);
+        assert_eq!(perft(&promotions, 2), 264);
+        assert_eq!(perft(&promotions, 3), 9467);
+
+        let discovered = from_fen("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8");
+        assert_eq!(perft(&discovered, 1), 44);
+        assert_eq!(perft(&discovered, 2), 1486);
+        assert_eq!(perft(&discovered, 3), 62379);
+    }
+
+    #[test]
+    fn no_castling_out_of_check() {
+        let checked = from_fen("4k3/8/8/8/4r3/8/8/R3K2R w KQ - 0 1");
+        let king = targets(&checked, "e1");
+        assert!(!king.contains(&square("g1")));
+        assert!(!king.contains(&square("c1")));
+    }
+
+    #[test]
+    fn no_castling_through_or_into_check() {
+        let through = from_fen("4kr2/8/8/8/8/8/8/R3K2R w KQ - 0 1");
+        let king = targets(&through, "e1");
+        assert!(!king.contains(&square("g1")));
+        assert!(king.contains(&square("c1")));
+
+        let into = from_fen("4k1r1/8/8/8/8/8/8/R3K2R w KQ - 0 1");
+        assert!(!targets(&into, "e1").contains(&square("g1")));
+
+        // the rook passes b1 but the king doesn't
+        let rook_passes = from_fen("1r2k3/8/8/8/8/8/8/R3K2R w KQ - 0 1");
+        assert!(targets(&rook_passes, "e1").contains(&square("c1")));
+    }
+
+    #[test]
+    fn castling_moves_the_rook_and_drops_the_rights() {
+        let mut position = from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        assert!(position.play(Move::new(square("e1"), square("g1"))));
+        assert_eq!(
+            position.piece(square("f1")),
+            Some(Piece::new(Color::White, Type::Rook))
+        );
+        assert_eq!(position.piece(square("h1")), None);
+        assert!(!position.castling.white_king_side && !position.castling.white_queen_side);
+
+        // taking a rook takes away the right to castle with it
+        assert!(position.play(Move::new(square("a8"), square("a1"))));
+        assert!(!position.castling.black_queen_side && position.castling.black_king_side);
+    }
+
+    #[test]
+    fn en_passant() {
+        let mut position = from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1");
+        assert!(position.is_capture(Move::new(square("e5"), square("d6"))));
+        assert!(!position.is_capture(Move::new(square("e5"), square("e6"))));
+        assert!(position.play(Move::new(square("e5"), square("d6"))));
+        assert_eq!(position.piece(square("d5")), None);
+        assert_eq!(
+            position.piece(square("d6")),
+            Some(Piece::new(Color::White, Type::Pawn))
+        );
+
+        // taking would leave both pawns off the fifth rank and the king open to the rook
+        let pinned = from_fen("8/8/8/K2pP2r/8/8/8/7k w - d6 0 1");
+        assert_eq!(targets(&pinned, "e5"), vec![square("e6")]);
+    }
+
+    #[test]
+    fn double_step_allows_en_passant_once() {
+        let mut position = Position::default();
+        assert!(position.play(Move::new(square("e2"), square("e4"))));
+        assert_eq!(position.en_passant, Some(square("e3")));
+        assert!(position.play(Move::new(square("g8"), square("f6"))));
+        assert_eq!(position.en_passant, None);
+    }
+
+    #[test]
+    fn underpromotion() {
+        let mut position = from_fen("8/P7/8/8/8/8/8/k6K w - - 0 1");
+        let promotions: Vec<_> = position
+            .legal_moves_from(square("a7"))
+            .iter()
+            .map(|mv| mv.promotion)
+            .collect();
+        assert_eq!(
+            promotions,
+            PROMOTIONS.iter().copied().map(Some).collect::<Vec<_>>()
+        );
+        // a pawn can't reach the last rank without choosing a piece
+        assert!(!position.play(Move::new(square("a7"), square("a8"))));
+
+        let knight = Move {
+            promotion: Some(Type::Knight),
+            ..Move::new(square("a7"), square("a8"))
+        };
+        assert!(position.play(knight));
+        assert_eq!(
+            position.piece(square("a8")),
+            Some(Piece::new(Color::White, Type::Knight))
+        );
+    }
+
+    #[test]
+    fn pinned_piece_stays_on_the_line() {
+        let pinned = from_fen("4r1k1/8/8/8/8/8/4B3/4K3 w - - 0 1");
+        assert!(targets(&pinned, "e2").is_empty());
+        let pinned = from_fen("4r1k1/8/8/8/8/8/4R3/4K3 w - - 0 1");
+        let rook = targets(&pinned, "e2");
+        assert!(rook.iter().all(|to| to.file == 4));
+        assert!(rook.contains(&square("e8")));
+    }
+
+    #[test]
+    fn move_counters() {
+        let mut position = Position::default();
+        assert!(position.play(Move::new(square("g1"), square("f3"))));
+        assert_eq!((position.halfmove_clock, position.fullmove_number), (1, 1));
+        assert!(position.play(Move::new(square("g8"), square("f6"))));
+        assert_eq!((position.halfmove_clock, position.fullmove_number), (2, 2));
+        assert!(position.play(Move::new(square("e2"), square("e4"))));
+        assert_eq!((position.halfmove_clock, position.fullmove_number), (0, 2));
+        assert!(position.play(Move::new(square("f6"), square("e4"))));
+        assert_eq!((position.halfmove_clock, position.fullmove_number), (0, 3));
+    }
+
+    #[test]
+    fn mate_and_stalemate() {
+        let mut fools_mate = Position::default();
+        for (from, to) in &[("f2", "f3"), ("e7", "e5"), ("g2", "g4"), ("d8", "h4")] {
+            assert!(fools_mate.play(Move::new(square(from), square(to))));
+        }
+        assert!(fools_mate.is_checkmate());
+
+        let stalemate = from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1");
+        assert!(stalemate.is_stalemate());
+    }
+
+    #[test]
+    fn fen() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(from_fen(start), Position::default());
+        // the move counters may be left out
+        assert_eq!(
+            from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -"),
+            Position::default()
+        );
+
+        let position = from_fen("4k3/8/8/3pP3/8/8/8/4K3 b Kq d6 12 40");
+        assert_eq!(position.turn, Color::Black);
+        assert_eq!(
+            position.castling,
+            CastlingRights {
+                white_king_side: true,
+                black_queen_side: true,
+                ..CastlingRights::NONE
+            }
+        );
+        assert_eq!(position.en_passant, Some(square("d6")));
+        assert_eq!(
+            (position.halfmove_clock, position.fullmove_number),
+            (12, 40)
+        );
+        assert_eq!(
+            position.piece(square("e5")),
+            Some(Piece::new(Color::White, Type::Pawn))
+        );
+    }
+
+    #[test]
+    fn malformed_fen() {
+        let error = |fen: &str| Position::from_fen(fen).unwrap_err();
+        assert_eq!(error("8/8/8/8/8/8/8/8 w -"), FenError::Fields(3));
+        assert_eq!(error("8/8/8/8/8/8/8/8 w - - 0 1 x"), FenError::Fields(7));
+        for placement in &[
+            "8/8/8/8/8/8/8",
+            "8/8/8/8/8/8/8/8/8",
+            "8/8/8/8/8/8/8/7",
+            "8/8/8/8/8/8/8/9",
+            "8/8/8/8/8/8/8/44k",
+            "8/8/8/8/8/8/8/k07",
+            "8/8/8/8/8/8/8/x7",
+        ] {
+            assert_eq!(
+                error(&format!("{} w - -", placement)),
+                FenError::Placement((*placement).to_owned())
+            );
+        }
+        assert_eq!(error("8/8/8/8/8/8/8/8 x - -"), FenError::Turn("x".into()));
+        assert_eq!(
+            error("8/8/8/8/8/8/8/8 w KX -"),
+            FenError::Castling("KX".into())
+        );
+        assert_eq!(
+            error("8/8/8/8/8/8/8/8 w - e9"),
+            FenError::EnPassant("e9".into())
+        );
+        assert_eq!(
+            error("8/8/8/8/8/8/8/8 w - - -1 1"),
+            FenError::Counter("-1".into())
+        );
+        assert_eq!(
+            error("8/8/8/8/8/8/8/8 w - - 0 x"),
+            FenError::Counter("x".into())
+        );
+    }
+}
@@ -0,0 +1,267 @@
+use crate::board::Square;
+
+/// decides which pieces can be picked up and where they can go
+pub trait MoveValidator {
+    /// whether the piece on `square` may be selected, usually whether it's that side's turn
+    fn is_movable(&self, square: Square) -> bool;
+
+    /// squares the piece on `from` can legally move to
+    fn legal_targets(&self, from: Square) -> Vec<Square>;
+}
+
+/// what a click did to the selection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionEvent {
+    /// a piece was picked up, its targets are in `Selection::targets`
+    Selected(Square),
+    Deselected,
+    /// the selected piece should move to `to`, the selection has been cleared
+    Move {
+        from: Square,
+        to: Square,
+    },
+    Ignored,
+}
+
+/// click-to-move and drag-and-drop state: click a piece to select it, then click one of its
+/// targets to move or anywhere else to deselect. pressing on a piece also picks it up so it
+/// can be dropped straight onto a target
+#[derive(Debug, Clone, Default)]
+pub struct Selection {
+    selected: Option<Square>,
+    targets: Vec<Square>,
+    dragging: Option<Square>,
+    /// the dragged piece was already selected before it was pressed
+    reselected: bool,
+}
+
+impl Selection {
+    pub fn selected(&self) -> Option<Square> {
+        self.selected
+    }
+
+    pub fn targets(&self) -> &[Square] {
+        &self.targets
+    }
+
+    /// the square of the piece being dragged, if any
+    pub fn dragging(&self) -> Option<Square> {
+        self.dragging
+    }
+
+    pub fn clear(&mut self) {
+        self.selected = None;
+        self.targets.clear();
+        self.dragging = None;
+    }
+
+    /// handles a click on `square`, `None` meaning the click missed the board
+    pub fn click(
+        &mut self,
+        square: Option<Square>,
+        validator: &impl MoveValidator,
+    ) -> SelectionEvent {
+        match (self.selected, square) {
+            (Some(from), Some(to)) if self.targets.contains(&to) => {
+                self.clear();
+                SelectionEvent::Move { from, to }
+            }
+            (Some(from), Some(square)) if from != square && validator.is_movable(square) => {
+                self.select(square, validator)
+            }
+            (Some(_), _) => {
+                self.clear();
+                SelectionEvent::Deselected
+            }
+            (None, Some(square)) if validator.is_movable(square) => self.select(square, validator),
+            (None, _) => SelectionEvent::Ignored,
+        }
+    }
+
+    /// handles the mouse button going down on `square`, which starts a drag if there's a
+    /// movable piece there
+    pub fn press(
+        &mut self,
+        square: Option<Square>,
+        validator: &impl MoveValidator,
+    ) -> SelectionEvent {
+        let square = match square {
+            Some(square) if validator.is_movable(square) => square,
+            _ => return self.click(square, validator),
+        };
+        if self.selected == Some(square) {
+            self.reselected = true;
+            self.dragging = Some(square);
+            return SelectionEvent::Ignored;
+        }
+        let event = self.click(Some(square), validator);
+        self.reselected = false;
+        self.dragging = self.selected;
+        event
+    }
+
+    /// handles the mouse button coming up over `square`, dropping the dragged piece
+    pub fn release(&mut self, square: Option<Square>) -> SelectionEvent {
+        let from = match self.dragging.take() {
+            Some(from) => from,
+            None => return SelectionEvent::Ignored,
+        };
+        match square {
+            // released without moving off the piece, so it was just a click
+            Some(to) if to == from && !self.reselected => SelectionEvent::Ignored,
+            Some(to) if self.targets.contains(&to) => {
+                self.clear();
+                SelectionEvent::Move { from, to }
+            }
+            _ => {
+                self.clear();
+                SelectionEvent::Deselected
+            }
+        }
+    }
+
+    fn select(&mut self, square: Square, validator: &impl MoveValidator) -> SelectionEvent {
+        self.selected = Some(square);
+        self.targets = validator.legal_targets(square);
+        SelectionEvent::Selected(square)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::Position;
+
+    fn square(name: &str) -> Square {
+        Square::from_name(name).unwrap()
+    }
+
+    fn click(selection: &mut Selection, name: &str) -> SelectionEvent {
+        selection.click(Some(square(name)), &Position::default())
+    }
+
+    #[test]
+    fn click_click_move() {
+        let mut selection = Selection::default();
+        assert_eq!(
+            click(&mut selection, "e2"),
+            SelectionEvent::Selected(square("e2"))
+        );
+        assert_eq!(selection.selected(), Some(square("e2")));
+        assert_eq!(selection.targets().len(), 2);
+        assert_eq!(
+            click(&mut selection, "e4"),
+            SelectionEvent::Move {
+                from: square("e2"),
+                to: square("e4"),
+            }
+        );
+        assert_eq!(selection.selected(), None);
+        assert!(selection.targets().is_empty());
+    }
+
+    #[test]
+    fn pieces_of_the_side_not_to_move_are_ignored() {
+        let mut selection = Selection::default();
+        assert_eq!(click(&mut selection, "e7"), SelectionEvent::Ignored);
+        assert_eq!(click(&mut selection, "e4"), SelectionEvent::Ignored);
+        assert_eq!(
+            selection.click(None, &Position::default()),
+            SelectionEvent::Ignored
+        );
+        assert_eq!(selection.selected(), None);
+    }
+
+    #[test]
+    fn reselecting_another_piece() {
+        let mut selection = Selection::default();
+        click(&mut selection, "e2");
+        assert_eq!(
+            click(&mut selection, "g1"),
+            SelectionEvent::Selected(square("g1"))
+        );
+        let mut targets = selection.targets().to_vec();
+        targets.sort_by_key(|s| s.file);
+        assert_eq!(targets, vec![square("f3"), square("h3")]);
+    }
+
+    #[test]
+    fn deselect_click() {
+        let position = Position::default();
+        for miss in &[
+            Some(square("e5")),
+            Some(square("e7")),
+            Some(square("e2")),
+            None,
+        ] {
+            let mut selection = Selection::default();
+            click(&mut selection, "e2");
+            assert_eq!(
+                selection.click(*miss, &position),
+                SelectionEvent::Deselected
+            );
+            assert_eq!(selection.selected(), None);
+            assert!(selection.targets().is_empty());
+        }
+    }
+
+    #[test]
+    fn drag_and_drop() {
+        let position = Position::default();
+        let mut selection = Selection::default();
+        assert_eq!(
+            selection.press(Some(square("b1")), &position),
+            SelectionEvent::Selected(square("b1"))
+        );
+        assert_eq!(selection.dragging(), Some(square("b1")));
+        assert_eq!(
+            selection.release(Some(square("c3"))),
+            SelectionEvent::Move {
+                from: square("b1"),
+                to: square("c3"),
+            }
+        );
+        assert_eq!(selection.dragging(), None);
+        assert_eq!(selection.selected(), None);
+    }
+
+    #[test]
+    fn drag_onto_illegal_square() {
+        let position = Position::default();
+        let mut selection = Selection::default();
+        selection.press(Some(square("e2")), &position);
+        assert_eq!(
+            selection.release(Some(square("e5"))),
+            SelectionEvent::Deselected
+        );
+        assert_eq!(selection.selected(), None);
+        assert_eq!(selection.dragging(), None);
+        assert_eq!(
+            selection.release(Some(square("e4"))),
+            SelectionEvent::Ignored
+        );
+    }
+
+    #[test]
+    fn press_and_release_in_place() {
+        let position = Position::default();
+        let mut selection = Selection::default();
+        // the first press and release only selects
+        selection.press(Some(square("e2")), &position);
+        assert_eq!(
+            selection.release(Some(square("e2"))),
+            SelectionEvent::Ignored
+        );
+        assert_eq!(selection.selected(), Some(square("e2")));
+        // doing it again on the selected piece deselects it
+        assert_eq!(
+            selection.press(Some(square("e2")), &position),
+            SelectionEvent::Ignored
+        );
+        assert_eq!(
+            selection.release(Some(square("e2"))),
+            SelectionEvent::Deselected
+        );
+        assert_eq!(selection.selected(), None);
+    }
+}
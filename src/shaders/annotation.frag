@@ -0,0 +1,43 @@
+#version 450
+
+layout(location = 0) in vec2 v_local;
+layout(location = 1) in flat vec4 v_color;
+layout(location = 2) in flat vec3 v_size;
+layout(location = 3) in flat uint v_shape;
+
+layout(location = 0) out vec4 f_color;
+
+const uint RING = 0u;
+const uint SHAFT = 1u;
+
+// signed distances, negative inside the shape
+
+float ring(vec2 p, float radius, float thickness) {
+    return abs(length(p) - (radius - thickness / 2.0)) - thickness / 2.0;
+}
+
+float box(vec2 p, vec2 half_size) {
+    vec2 d = abs(p) - half_size;
+    return length(max(d, 0.0)) + min(max(d.x, d.y), 0.0);
+}
+
+// isosceles triangle with its base on -x and its tip on +x
+float head(vec2 p, vec2 half_size) {
+    vec2 edge_normal = normalize(vec2(half_size.y, 2.0 * half_size.x));
+    float edge = dot(vec2(p.x - half_size.x, abs(p.y)), edge_normal);
+    float base = -half_size.x - p.x;
+    return max(edge, base);
+}
+
+void main() {
+    float dist;
+    if (v_shape == RING) {
+        dist = ring(v_local, v_size.x, v_size.z);
+    } else if (v_shape == SHAFT) {
+        dist = box(v_local, v_size.xy);
+    } else {
+        dist = head(v_local, v_size.xy);
+    }
+    float coverage = clamp(0.5 - dist / fwidth(dist), 0.0, 1.0);
+    f_color = vec4(v_color.rgb, v_color.a * coverage);
+}
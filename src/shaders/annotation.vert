@@ -0,0 +1,34 @@
+#version 450
+
+layout(location = 0) in vec2 a_position;
+layout(location = 1) in vec2 a_texcoord;
+
+layout(location = 2) in vec4 a_shape_pos_0;
+layout(location = 3) in vec4 a_shape_pos_1;
+layout(location = 4) in vec4 a_shape_pos_2;
+layout(location = 5) in vec4 a_shape_pos_3;
+layout(location = 6) in vec4 a_color;
+layout(location = 7) in vec3 a_size;
+layout(location = 8) in uint a_shape;
+
+layout(location = 0) out vec2 v_local;
+layout(location = 1) out flat vec4 v_color;
+layout(location = 2) out flat vec3 v_size;
+layout(location = 3) out flat uint v_shape;
+
+// padding in squares around each shape so the anti-aliased edge isn't clipped by the quad
+const float PADDING = 0.05;
+
+void main() {
+    mat4 model_matrix = mat4(
+        a_shape_pos_0,
+        a_shape_pos_1,
+        a_shape_pos_2,
+        a_shape_pos_3
+    );
+    v_local = a_position * (a_size.xy + PADDING);
+    v_color = a_color;
+    v_size = a_size;
+    v_shape = a_shape;
+    gl_Position = model_matrix * vec4(v_local, 0.0, 1.0);
+}
@@ -0,0 +1,13 @@
+#version 450
+
+layout(location = 0) in vec2 v_texcoord;
+
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform texture2D t_board;
+layout(set = 0, binding = 1) uniform sampler s_board;
+
+void main() {
+    // the quad's texture coordinates go up the screen but textures are stored top down
+    f_color = texture(sampler2D(t_board, s_board), vec2(v_texcoord.x, 1.0 - v_texcoord.y));
+}
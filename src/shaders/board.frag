@@ -4,18 +4,44 @@ layout(location = 0) in vec2 v_texcoord;
 
 layout(location = 0) out vec4 f_color;
 
+layout(set = 0, binding = 0) uniform Theme {
+    vec4 light;
+    vec4 dark;
+    vec4 last_move;
+    vec4 selected;
+    vec4 check;
+    // bitmasks of highlighted squares indexed by rank * 8 + file, low word first
+    uvec2 last_move_squares;
+    uvec2 selected_squares;
+    uvec2 check_squares;
+};
+
 const float SIZE = 8.0;
-const vec3 foreground = vec3(0.6, 0.4, 0.2);
-const vec3 background = vec3(0.94, 0.85, 0.71);
 
 float toBoardCoord(in float coord) {
     return mod(coord * SIZE, 2.0);
 }
 
+bool isHighlighted(in uvec2 mask, in uint index) {
+    uint word = index < 32u ? mask.x : mask.y;
+    return ((word >> (index % 32u)) & 1u) == 1u;
+}
+
+vec3 highlight(in vec3 color, in uvec2 mask, in uint index, in vec4 highlight_color) {
+    return isHighlighted(mask, index)
+        ? mix(color, highlight_color.rgb, highlight_color.a)
+        : color;
+}
+
 void main() {
     vec3 val =
         toBoardCoord(v_texcoord.x) <= 1.0 ^^
         toBoardCoord(v_texcoord.y) <= 1.0
-        ? background : foreground;
+        ? light.rgb : dark.rgb;
+    uvec2 square = uvec2(min(v_texcoord * SIZE, vec2(SIZE - 1.0)));
+    uint index = square.y * 8u + square.x;
+    val = highlight(val, last_move_squares, index, last_move);
+    val = highlight(val, selected_squares, index, selected);
+    val = highlight(val, check_squares, index, check);
     f_color = vec4(val, 1.0);
 }
@@ -0,0 +1,24 @@
+#version 450
+
+layout(location = 0) in vec2 v_texcoord;
+layout(location = 1) in flat uint v_marker_kind;
+
+layout(location = 0) out vec4 f_color;
+
+const uint DOT = 0u;
+const float DOT_RADIUS = 0.3;
+const float RING_INNER_RADIUS = 0.82;
+const vec4 color = vec4(0.08, 0.33, 0.12, 0.5);
+
+void main() {
+    float dist = length(v_texcoord * 2.0 - 1.0);
+    float aa = fwidth(dist);
+    float alpha;
+    if (v_marker_kind == DOT) {
+        alpha = 1.0 - smoothstep(DOT_RADIUS - aa, DOT_RADIUS + aa, dist);
+    } else {
+        alpha = smoothstep(RING_INNER_RADIUS - aa, RING_INNER_RADIUS + aa, dist)
+            * (1.0 - smoothstep(1.0 - aa, 1.0, dist));
+    }
+    f_color = vec4(color.rgb, color.a * alpha);
+}
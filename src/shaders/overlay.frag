@@ -0,0 +1,19 @@
+#version 450
+
+layout(location = 0) in vec2 v_texcoord;
+layout(location = 1) in flat vec4 v_color;
+layout(location = 2) in flat uint v_shape;
+
+layout(location = 0) out vec4 f_color;
+
+const uint RECT = 0u;
+
+void main() {
+    float coverage = 1.0;
+    if (v_shape != RECT) {
+        float dist = length(v_texcoord * 2.0 - 1.0);
+        float aa = fwidth(dist);
+        coverage = 1.0 - smoothstep(1.0 - aa, 1.0, dist);
+    }
+    f_color = vec4(v_color.rgb, v_color.a * coverage);
+}
@@ -0,0 +1,28 @@
+#version 450
+
+layout(location = 0) in vec2 a_position;
+layout(location = 1) in vec2 a_texcoord;
+
+layout(location = 2) in vec4 a_overlay_pos_0;
+layout(location = 3) in vec4 a_overlay_pos_1;
+layout(location = 4) in vec4 a_overlay_pos_2;
+layout(location = 5) in vec4 a_overlay_pos_3;
+layout(location = 6) in vec4 a_color;
+layout(location = 7) in uint a_shape;
+
+layout(location = 0) out vec2 v_texcoord;
+layout(location = 1) out flat vec4 v_color;
+layout(location = 2) out flat uint v_shape;
+
+void main() {
+    mat4 model_matrix = mat4(
+        a_overlay_pos_0,
+        a_overlay_pos_1,
+        a_overlay_pos_2,
+        a_overlay_pos_3
+    );
+    v_texcoord = a_texcoord;
+    v_color = a_color;
+    v_shape = a_shape;
+    gl_Position = model_matrix * vec4(a_position, 0.0, 1.0);
+}
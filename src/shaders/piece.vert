@@ -8,10 +8,11 @@ layout(location = 3) in vec4 a_piece_pos_1;
 layout(location = 4) in vec4 a_piece_pos_2;
 layout(location = 5) in vec4 a_piece_pos_3;
 layout(location = 6) in uvec2 a_piece_type;
-
+layout(location = 7) in float a_piece_alpha;
 
 layout(location = 0) out vec2 v_texcoord;
 layout(location = 1) out flat uvec2 v_piece_type;
+layout(location = 2) out flat float v_piece_alpha;
 
 void main() {
     mat4 model_matrix = mat4(
@@ -22,5 +23,6 @@ void main() {
     );
     v_texcoord = a_texcoord;
     v_piece_type = a_piece_type;
+    v_piece_alpha = a_piece_alpha;
     gl_Position = model_matrix * vec4(a_position, 0.0, 1.0);
 }
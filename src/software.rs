@@ -0,0 +1,446 @@
+use std::{ops::Range, time::Duration};
+
+use image::{Rgba, RgbaImage};
+use nalgebra::Vector2;
+
+use crate::{
+    animation::Animation,
+    annotation::{Annotation, Primitive, Shape},
+    atlas::{linear_to_srgb, srgb_to_linear, AtlasUniform, MipmappedAtlas},
+    board::{Board, Orientation, Square},
+    coordinates::plot_coordinates,
+    markers::MarkerKind,
+    piece::Piece,
+    piece_set::{PieceSet, PieceSetError},
+    renderer::{PieceSetRenderer, Renderer},
+    theme::{Highlight, Highlights, Theme},
+};
+
+/// an atlas level converted to linear rgba, which is what the gpu filters an srgb texture in
+struct LinearLevel {
+    width: u32,
+    height: u32,
+    texels: Vec<[f32; 4]>,
+}
+
+impl LinearLevel {
+    fn new(level: &RgbaImage) -> LinearLevel {
+        let texels = level
+            .pixels()
+            .map(|&Rgba([r, g, b, a])| {
+                [
+                    srgb_to_linear(r),
+                    srgb_to_linear(g),
+                    srgb_to_linear(b),
+                    a as f32 / 255.0,
+                ]
+            })
+            .collect();
+        LinearLevel {
+            width: level.width(),
+            height: level.height(),
+            texels,
+        }
+    }
+
+    fn texel(&self, x: i64, y: i64) -> [f32; 4] {
+        let x = x.clamp(0, self.width as i64 - 1) as usize;
+        let y = y.clamp(0, self.height as i64 - 1) as usize;
+        self.texels[y * self.width as usize + x]
+    }
+
+    /// bilinear sample at normalized coordinates, clamping to the edges like the sampler
+    fn sample(&self, u: f32, v: f32) -> [f32; 4] {
+        let x = u * self.width as f32 - 0.5;
+        let y = v * self.height as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+        let top = mix(self.texel(x0, y0), self.texel(x0 + 1, y0), fx);
+        let bottom = mix(self.texel(x0, y0 + 1), self.texel(x0 + 1, y0 + 1), fx);
+        mix(top, bottom, fy)
+    }
+}
+
+// the marker shader's radii, as fractions of half a square
+const DOT_RADIUS: f32 = 0.3;
+const RING_INNER_RADIUS: f32 = 0.82;
+/// the marker shader's linear color
+const MARKER_COLOR: [f32; 4] = [0.08, 0.33, 0.12, 0.5];
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn mix(a: [f32; 4], b: [f32; 4], t: f32) -> [f32; 4] {
+    [
+        a[0] + (b[0] - a[0]) * t,
+        a[1] + (b[1] - a[1]) * t,
+        a[2] + (b[2] - a[2]) * t,
+        a[3] + (b[3] - a[3]) * t,
+    ]
+}
+
+/// draws the board, markers, pieces and annotations like `BoardRenderer` does but entirely on
+/// the cpu, for when there's no adapter at all such as on servers and in ci
+pub struct SoftwareRenderer {
+    theme: Theme,
+    highlights: Highlights,
+    orientation: Orientation,
+    coordinates: bool,
+    targets: Vec<(Square, MarkerKind)>,
+    annotations: Vec<Annotation>,
+    levels: Vec<LinearLevel>,
+    uniform: AtlasUniform,
+}
+
+impl SoftwareRenderer {
+    /// a renderer with the default theme, coordinates and the builtin pieces
+    pub fn new() -> Result<SoftwareRenderer, PieceSetError> {
+        let mut renderer = SoftwareRenderer {
+            theme: Theme::default(),
+            highlights: Highlights::default(),
+            orientation: Orientation::White,
+            coordinates: true,
+            targets: Vec::new(),
+            annotations: Vec::new(),
+            levels: Vec::new(),
+            uniform: AtlasUniform::default(),
+        };
+        renderer.set_piece_set(&PieceSet::builtin()?);
+        Ok(renderer)
+    }
+
+    /// marks `targets` with a dot, or a ring if they're in `captures`, like
+    /// `BoardRenderer::set_move_targets`
+    pub fn set_move_targets(&mut self, targets: &[Square], captures: &[Square]) {
+        self.targets = targets
+            .iter()
+            .map(|&square| {
+                let kind = if captures.contains(&square) {
+                    MarkerKind::Ring
+                } else {
+                    MarkerKind::Dot
+                };
+                (square, kind)
+            })
+            .collect();
+    }
+
+    pub fn clear_move_targets(&mut self) {
+        self.targets.clear();
+    }
+
+    /// draws `board` into a new `size`x`size` image, in srgb like a frame of an srgb swap chain
+    pub fn render(&self, board: &Board, size: u32) -> RgbaImage {
+        self.render_transition(board, board, 1.0, size)
+    }
+
+    /// draws the board partway through animating from `old` to `new`, with `progress` going
+    /// from 0 to 1 and already eased. pieces slide and fade as `BoardRenderer` animates them
+    pub fn render_transition(
+        &self,
+        old: &Board,
+        new: &Board,
+        progress: f32,
+        size: u32,
+    ) -> RgbaImage {
+        let size = size.max(1);
+        let square_size = size as f32 / 8.0;
+        let mut pixels = Vec::with_capacity((size * size) as usize);
+        for y in 0..size {
+            for x in 0..size {
+                let file = ((x as f32 + 0.5) / square_size).min(7.0) as usize;
+                let row = ((y as f32 + 0.5) / square_size).min(7.0) as usize;
+                let square = Square::new(file, 7 - row).oriented(self.orientation);
+                pixels.push(self.theme.highlighted_color(square, &self.highlights));
+            }
+        }
+        if self.coordinates {
+            plot_coordinates(size, &self.theme, self.orientation, |x, y, color| {
+                pixels[(y * size + x) as usize] = color;
+            });
+        }
+
+        for &(square, kind) in &self.targets {
+            self.draw_marker(&mut pixels, size, square.oriented(self.orientation), kind);
+        }
+
+        // the duration doesn't matter, the progress is given
+        let animation =
+            Animation::between(old, new, Duration::from_secs(0)).filter(|_| progress < 1.0);
+        let orientation = self.orientation;
+        if let Some(animation) = &animation {
+            for fade in &animation.fades {
+                let position = fade.position(orientation);
+                self.draw_piece(&mut pixels, size, fade.piece, position, 1.0 - progress);
+            }
+        }
+        for rank in 0..8 {
+            for file in 0..8 {
+                let square = Square::new(file, rank);
+                let is_moving = matches!(&animation, Some(a) if a.is_tween_target(square));
+                if let (Some(piece), false) = (square.piece(new), is_moving) {
+                    let oriented = square.oriented(orientation);
+                    let position = Vector2::new(oriented.file as f32, oriented.rank as f32);
+                    self.draw_piece(&mut pixels, size, piece, position, 1.0);
+                }
+            }
+        }
+        if let Some(animation) = &animation {
+            for tween in &animation.tweens {
+                let position = tween.position(progress, orientation);
+                self.draw_piece(&mut pixels, size, tween.piece, position, 1.0);
+            }
+        }
+
+        for annotation in &self.annotations {
+            let [r, g, b, a] = annotation.brush().rgba();
+            for primitive in annotation.primitives(orientation) {
+                draw_primitive(&mut pixels, size, &primitive, [r, g, b], a);
+            }
+        }
+
+        RgbaImage::from_fn(size, size, |x, y| {
+            let [r, g, b] = pixels[(y * size + x) as usize];
+            Rgba([linear_to_srgb(r), linear_to_srgb(g), linear_to_srgb(b), 255])
+        })
+    }
+
+    /// blends the dot or ring of `marker.frag` over `square`, which is already oriented
+    fn draw_marker(&self, pixels: &mut [[f32; 3]], size: u32, square: Square, kind: MarkerKind) {
+        let square_size = size as f32 / 8.0;
+        let left = square.file as f32 * square_size;
+        let top = (7 - square.rank) as f32 * square_size;
+        // the distance below is in half squares
+        let aa = 2.0 / square_size;
+        let [r, g, b, a] = MARKER_COLOR;
+        for y in covered(top, square_size, size) {
+            for x in covered(left, square_size, size) {
+                let u = (x as f32 + 0.5 - left) / square_size * 2.0 - 1.0;
+                let v = (y as f32 + 0.5 - top) / square_size * 2.0 - 1.0;
+                let dist = (u * u + v * v).sqrt();
+                let alpha = match kind {
+                    MarkerKind::Dot => 1.0 - smoothstep(DOT_RADIUS - aa, DOT_RADIUS + aa, dist),
+                    MarkerKind::Ring => {
+                        smoothstep(RING_INNER_RADIUS - aa, RING_INNER_RADIUS + aa, dist)
+                            * (1.0 - smoothstep(1.0 - aa, 1.0, dist))
+                    }
+                };
+                blend(&mut pixels[(y * size + x) as usize], [r, g, b], a * alpha);
+            }
+        }
+    }
+
+    /// blends `piece` over the square at `position`, in squares from the bottom left of the
+    /// board as shown, sampling the atlas trilinearly like the pieces' sampler
+    fn draw_piece(
+        &self,
+        pixels: &mut [[f32; 3]],
+        size: u32,
+        piece: Piece,
+        position: Vector2<f32>,
+        opacity: f32,
+    ) {
+        let square_size = size as f32 / 8.0;
+        let left = position.x * square_size;
+        let top = (7.0 - position.y) * square_size;
+
+        let cell_texels = self.levels[0].width as f32 / 6.0 * self.uniform.scale;
+        let lod = (cell_texels / square_size)
+            .log2()
+            .clamp(0.0, (self.levels.len() - 1) as f32);
+        let (near, far) = (lod.floor() as usize, lod.ceil() as usize);
+
+        for y in covered(top, square_size, size) {
+            for x in covered(left, square_size, size) {
+                // the quad's texture coordinates, with y going up like in `piece.frag`
+                let s = (x as f32 + 0.5 - left) / square_size;
+                let t = 1.0 - (y as f32 + 0.5 - top) / square_size;
+                let u =
+                    (piece.type_ as u32 as f32 + self.uniform.offset[0] + s * self.uniform.scale)
+                        / 6.0;
+                let v =
+                    (piece.color as u32 as f32 + self.uniform.offset[1] + t * self.uniform.scale)
+                        / 2.0;
+                let color = mix(
+                    self.levels[near].sample(u, v),
+                    self.levels[far].sample(u, v),
+                    lod - near as f32,
+                );
+
+                let pixel = &mut pixels[(y * size + x) as usize];
+                blend(pixel, [color[0], color[1], color[2]], color[3] * opacity);
+            }
+        }
+    }
+}
+
+/// the pixels from `start` to `start + length`, which are covered when their center is, the
+/// same rule the gpu rasterizes with
+fn covered(start: f32, length: f32, size: u32) -> Range<u32> {
+    ((start - 0.5).ceil().max(0.0) as u32)
+        ..((start + length - 0.5).ceil().max(0.0) as u32).min(size)
+}
+
+fn blend(pixel: &mut [f32; 3], color: [f32; 3], alpha: f32) {
+    for (channel, &value) in pixel.iter_mut().zip(&color) {
+        *channel = value * alpha + *channel * (1.0 - alpha);
+    }
+}
+
+/// blends an annotation's shape like `annotation.frag`, from the same signed distances
+fn draw_primitive(
+    pixels: &mut [[f32; 3]],
+    size: u32,
+    primitive: &Primitive,
+    color: [f32; 3],
+    alpha: f32,
+) {
+    let square_size = size as f32 / 8.0;
+    // the shader's quad is padded so the anti-aliased edge isn't clipped
+    let reach = primitive.half_size.norm() + 0.05;
+    let left = (primitive.center.x - reach) * square_size;
+    let top = (8.0 - primitive.center.y - reach) * square_size;
+    let extent = reach * 2.0 * square_size;
+    let dir = primitive.dir;
+    for y in covered(top, extent, size) {
+        for x in covered(left, extent, size) {
+            let point = Vector2::new(
+                (x as f32 + 0.5) / square_size,
+                8.0 - (y as f32 + 0.5) / square_size,
+            ) - primitive.center;
+            // into the shape's frame, where x runs along `dir`
+            let local = Vector2::new(point.dot(&dir), dir.x * point.y - dir.y * point.x);
+            let dist = match primitive.shape {
+                Shape::Ring => {
+                    let (radius, thickness) = (primitive.half_size.x, primitive.thickness);
+                    (local.norm() - (radius - thickness / 2.0)).abs() - thickness / 2.0
+                }
+                Shape::Shaft => {
+                    let d = local.abs() - primitive.half_size;
+                    let outside = Vector2::new(d.x.max(0.0), d.y.max(0.0)).norm();
+                    outside + d.x.max(d.y).min(0.0)
+                }
+                Shape::Head => {
+                    let half_size = primitive.half_size;
+                    let normal = Vector2::new(half_size.y, 2.0 * half_size.x).normalize();
+                    let edge = Vector2::new(local.x - half_size.x, local.y.abs()).dot(&normal);
+                    edge.max(-half_size.x - local.x)
+                }
+            };
+            // the distance changes by a square every `square_size` pixels
+            let coverage = (0.5 - dist * square_size).clamp(0.0, 1.0);
+            blend(
+                &mut pixels[(y * size + x) as usize],
+                color,
+                alpha * coverage,
+            );
+        }
+    }
+}
+
+impl Renderer for SoftwareRenderer {
+    fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    fn set_highlight(&mut self, kind: Highlight, squares: &[Square]) {
+        self.highlights.set(kind, squares);
+    }
+
+    fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
+    fn set_orientation(&mut self, orientation: Orientation) {
+        self.orientation = orientation;
+    }
+
+    fn set_coordinates(&mut self, coordinates: bool) {
+        self.coordinates = coordinates;
+    }
+
+    fn set_annotations(&mut self, annotations: &[Annotation]) {
+        self.annotations.clear();
+        for &annotation in annotations {
+            if !self.annotations.contains(&annotation) {
+                self.annotations.push(annotation);
+            }
+        }
+    }
+}
+
+impl PieceSetRenderer for SoftwareRenderer {
+    fn set_piece_set(&mut self, set: &PieceSet) {
+        let atlas = MipmappedAtlas::new(set);
+        self.levels = atlas.levels.iter().map(LinearLevel::new).collect();
+        self.uniform = atlas.uniform;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{annotation::Brush, board::default_board};
+
+    fn square(name: &str) -> Square {
+        Square::from_name(name).unwrap()
+    }
+
+    /// a pixel just above the middle of `name` and one near its left edge, on a 160 pixel
+    /// board
+    fn samples(image: &RgbaImage, name: &str) -> (Rgba<u8>, Rgba<u8>) {
+        let square = square(name);
+        let (x, y) = (square.file as u32 * 20, (7 - square.rank as u32) * 20);
+        (
+            *image.get_pixel(x + 10, y + 8),
+            *image.get_pixel(x + 1, y + 10),
+        )
+    }
+
+    #[test]
+    fn markers() {
+        let mut renderer = SoftwareRenderer::new().unwrap();
+        let plain = renderer.render(&[[None; 8]; 8], 160);
+        renderer.set_move_targets(&[square("e3"), square("d5")], &[square("d5")]);
+        let marked = renderer.render(&[[None; 8]; 8], 160);
+
+        let (center, edge) = samples(&marked, "e3");
+        assert_ne!(center, samples(&plain, "e3").0);
+        assert_eq!(edge, samples(&plain, "e3").1);
+        let (center, edge) = samples(&marked, "d5");
+        assert_eq!(center, samples(&plain, "d5").0);
+        assert_ne!(edge, samples(&plain, "d5").1);
+
+        renderer.clear_move_targets();
+        assert_eq!(renderer.render(&[[None; 8]; 8], 160), plain);
+    }
+
+    #[test]
+    fn annotations_are_drawn_over_pieces() {
+        let mut renderer = SoftwareRenderer::new().unwrap();
+        let board = default_board();
+        let plain = renderer.render(&board, 160);
+        renderer.set_annotations(&[Annotation::Arrow {
+            from: square("e2"),
+            to: square("e4"),
+            brush: Brush::Red,
+        }]);
+        let annotated = renderer.render(&board, 160);
+        // the head's tip is in the middle of e4
+        for name in &["e2", "e3"] {
+            assert_ne!(samples(&annotated, name).0, samples(&plain, name).0);
+        }
+        assert_eq!(samples(&annotated, "e5").0, samples(&plain, "e5").0);
+        assert_eq!(samples(&annotated, "d3").0, samples(&plain, "d3").0);
+
+        renderer.set_annotations(&[]);
+        assert_eq!(renderer.render(&board, 160), plain);
+    }
+}
@@ -0,0 +1,392 @@
+use std::{collections::HashMap, fmt::Write};
+
+use image::{png::PngEncoder, ColorType};
+use nalgebra::Vector2;
+
+use crate::{
+    annotation::{Annotation, Primitive, Shape},
+    atlas::linear_to_srgb,
+    board::{Board, Orientation, Square},
+    piece::Piece,
+    piece_set::{all_pieces, PieceSet, PieceSetError, SvgPieceSet},
+    renderer::{PieceSetRenderer, Renderer},
+    theme::{Highlight, Highlights, Theme},
+};
+
+/// size of the coordinates as a fraction of a square
+const COORDINATE_SIZE: f32 = 0.2;
+
+/// writes the board as a standalone svg document, for diagrams in documents and anywhere
+/// else there's no gpu. pieces are embedded as data urls, which stay vectors with an
+/// `SvgPieceSet`
+pub struct SvgRenderer {
+    theme: Theme,
+    highlights: Highlights,
+    orientation: Orientation,
+    annotations: Vec<Annotation>,
+    coordinates: bool,
+    /// the data url of each piece's image
+    pieces: HashMap<Piece, String>,
+}
+
+impl SvgRenderer {
+    /// a renderer with the default theme, coordinates and the builtin pieces
+    pub fn new() -> Result<SvgRenderer, PieceSetError> {
+        let mut renderer = SvgRenderer {
+            theme: Theme::default(),
+            highlights: Highlights::default(),
+            orientation: Orientation::White,
+            annotations: Vec::new(),
+            coordinates: true,
+            pieces: HashMap::new(),
+        };
+        renderer.set_piece_set(&PieceSet::builtin()?);
+        Ok(renderer)
+    }
+
+    /// embeds the svgs themselves so pieces are as sharp as the rest of the board
+    pub fn set_svg_piece_set(&mut self, set: &SvgPieceSet) {
+        self.pieces = all_pieces()
+            .map(|piece| {
+                let url = data_url("image/svg+xml", set.to_svg(piece).as_bytes());
+                (piece, url)
+            })
+            .collect();
+    }
+
+    pub fn annotations(&self) -> &[Annotation] {
+        &self.annotations
+    }
+
+    /// draws an arrow or circle over the pieces, returns false if it's already shown
+    pub fn add_annotation(&mut self, annotation: Annotation) -> bool {
+        if self.annotations.contains(&annotation) {
+            return false;
+        }
+        self.annotations.push(annotation);
+        true
+    }
+
+    /// returns false if the annotation wasn't shown
+    pub fn remove_annotation(&mut self, annotation: &Annotation) -> bool {
+        let len = self.annotations.len();
+        self.annotations.retain(|a| a != annotation);
+        self.annotations.len() != len
+    }
+
+    pub fn clear_annotations(&mut self) {
+        self.annotations.clear();
+    }
+
+    /// draws `board` as a `size`x`size` svg document
+    pub fn render(&self, board: &Board, size: u32) -> String {
+        let square_size = size as f32 / 8.0;
+        let mut svg = String::new();
+        writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="{0}" height="{0}" viewBox="0 0 {0} {0}">"#,
+            size
+        )
+        .unwrap();
+
+        for row in 0..8 {
+            for column in 0..8 {
+                let square = Square::new(column, 7 - row).oriented(self.orientation);
+                writeln!(
+                    svg,
+                    r#"<rect x="{}" y="{}" width="{3}" height="{3}" fill="{}"/>"#,
+                    column as f32 * square_size,
+                    row as f32 * square_size,
+                    hex(self.theme.highlighted_color(square, &self.highlights)),
+                    square_size,
+                )
+                .unwrap();
+            }
+        }
+
+        if self.coordinates {
+            self.write_coordinates(&mut svg, square_size);
+        }
+
+        for rank in 0..8 {
+            for file in 0..8 {
+                let square = Square::new(file, rank);
+                if let Some(piece) = square.piece(board) {
+                    let oriented = square.oriented(self.orientation);
+                    writeln!(
+                        svg,
+                        r#"<image x="{}" y="{}" width="{3}" height="{3}" xlink:href="{}"/>"#,
+                        oriented.file as f32 * square_size,
+                        (7 - oriented.rank) as f32 * square_size,
+                        self.pieces[&piece],
+                        square_size,
+                    )
+                    .unwrap();
+                }
+            }
+        }
+
+        for annotation in &self.annotations {
+            // the shapes are blended as one so they don't darken where they overlap
+            let [r, g, b, a] = annotation.brush().rgba();
+            let color = hex([r, g, b]);
+            writeln!(svg, r#"<g fill="{}" opacity="{}">"#, color, a).unwrap();
+            for primitive in annotation.primitives(self.orientation) {
+                write_primitive(&mut svg, &primitive, &color, square_size);
+            }
+            svg.push_str("</g>\n");
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// the ranks down the left edge and the files along the bottom, each in the color of
+    /// the other kind of square so they stand out
+    fn write_coordinates(&self, svg: &mut String, square_size: f32) {
+        let font_size = square_size * COORDINATE_SIZE;
+        let margin = font_size * 0.4;
+        for i in 0..8 {
+            let rank = Square::new(0, 7 - i).oriented(self.orientation);
+            writeln!(
+                svg,
+                r#"<text x="{}" y="{}" font-family="sans-serif" font-size="{}" font-weight="bold" dominant-baseline="hanging" fill="{}">{}</text>"#,
+                margin,
+                i as f32 * square_size + margin,
+                font_size,
+                hex(rgb(self.theme.square_color(Square::new(1, 7 - i)))),
+                rank.rank + 1,
+            )
+            .unwrap();
+
+            let file = Square::new(i, 0).oriented(self.orientation);
+            writeln!(
+                svg,
+                r#"<text x="{}" y="{}" font-family="sans-serif" font-size="{}" font-weight="bold" text-anchor="end" fill="{}">{}</text>"#,
+                (i + 1) as f32 * square_size - margin,
+                8.0 * square_size - margin,
+                font_size,
+                hex(rgb(self.theme.square_color(Square::new(i, 1)))),
+                (b'a' + file.file as u8) as char,
+            )
+            .unwrap();
+        }
+    }
+}
+
+impl Renderer for SvgRenderer {
+    fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    fn set_highlight(&mut self, kind: Highlight, squares: &[Square]) {
+        self.highlights.set(kind, squares);
+    }
+
+    fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
+    fn set_orientation(&mut self, orientation: Orientation) {
+        self.orientation = orientation;
+    }
+
+    fn set_coordinates(&mut self, coordinates: bool) {
+        self.coordinates = coordinates;
+    }
+
+    fn set_annotations(&mut self, annotations: &[Annotation]) {
+        self.clear_annotations();
+        for &annotation in annotations {
+            self.add_annotation(annotation);
+        }
+    }
+}
+
+impl PieceSetRenderer for SvgRenderer {
+    fn set_piece_set(&mut self, set: &PieceSet) {
+        let size = set.cell_size();
+        self.pieces = all_pieces()
+            .map(|piece| {
+                let mut png = Vec::new();
+                PngEncoder::new(&mut png)
+                    .encode(&set.image(piece), size, size, ColorType::Rgba8)
+                    .expect("encoding into memory can't fail");
+                (piece, data_url("image/png", &png))
+            })
+            .collect();
+    }
+}
+
+/// writes `primitive` in the svg's coordinates, which go down from the top left corner
+fn write_primitive(svg: &mut String, primitive: &Primitive, color: &str, square_size: f32) {
+    let point = |p: Vector2<f32>| (p.x * square_size, (8.0 - p.y) * square_size);
+    let (cx, cy) = point(primitive.center);
+    let half_size = primitive.half_size * square_size;
+    match primitive.shape {
+        Shape::Ring => {
+            let thickness = primitive.thickness * square_size;
+            writeln!(
+                svg,
+                r#"<circle cx="{}" cy="{}" r="{}" fill="none" stroke="{}" stroke-width="{}"/>"#,
+                cx,
+                cy,
+                half_size.x - thickness / 2.0,
+                color,
+                thickness,
+            )
+            .unwrap();
+        }
+        Shape::Shaft => {
+            let angle = -primitive.dir.y.atan2(primitive.dir.x).to_degrees();
+            writeln!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" transform="translate({} {}) rotate({})"/>"#,
+                -half_size.x,
+                -half_size.y,
+                half_size.x * 2.0,
+                half_size.y * 2.0,
+                cx,
+                cy,
+                angle,
+            )
+            .unwrap();
+        }
+        Shape::Head => {
+            let dir = primitive.dir;
+            let normal = Vector2::new(-dir.y, dir.x);
+            let base = primitive.center - dir * primitive.half_size.x;
+            let corners = [
+                primitive.center + dir * primitive.half_size.x,
+                base + normal * primitive.half_size.y,
+                base - normal * primitive.half_size.y,
+            ];
+            let points: Vec<String> = corners
+                .iter()
+                .map(|&corner| {
+                    let (x, y) = point(corner);
+                    format!("{},{}", x, y)
+                })
+                .collect();
+            writeln!(svg, r#"<polygon points="{}"/>"#, points.join(" ")).unwrap();
+        }
+    }
+}
+
+fn rgb([r, g, b, _]: [f32; 4]) -> [f32; 3] {
+    [r, g, b]
+}
+
+/// the `#rrggbb` form of a linear color
+fn hex([r, g, b]: [f32; 3]) -> String {
+    format!(
+        "#{:02x}{:02x}{:02x}",
+        linear_to_srgb(r),
+        linear_to_srgb(g),
+        linear_to_srgb(b)
+    )
+}
+
+fn data_url(mime: &str, data: &[u8]) -> String {
+    format!("data:{};base64,{}", mime, base64::encode(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        annotation::Brush,
+        board::default_board,
+        piece::{Color, Type},
+    };
+
+    const KING: Piece = Piece {
+        color: Color::White,
+        type_: Type::King,
+    };
+
+    /// the text of every `<text>` element, in order
+    fn labels(svg: &str) -> Vec<&str> {
+        svg.split("<text ")
+            .skip(1)
+            .map(|text| {
+                let start = text.find('>').unwrap() + 1;
+                &text[start..text.find("</text>").unwrap()]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn start_position() {
+        let renderer = SvgRenderer::new().unwrap();
+        let svg = renderer.render(&default_board(), 400);
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.contains(r#"width="400" height="400" viewBox="0 0 400 400""#));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<rect ").count(), 64);
+        assert_eq!(svg.matches("<image ").count(), 32);
+
+        // a8 is light and comes first
+        let light = hex(rgb(renderer.theme().light));
+        assert!(svg.contains(&format!(
+            r#"<rect x="0" y="0" width="50" height="50" fill="{}"/>"#,
+            light
+        )));
+        let king = format!(
+            r#"<image x="200" y="350" width="50" height="50" xlink:href="{}"/>"#,
+            renderer.pieces[&KING]
+        );
+        assert!(svg.contains(&king));
+
+        assert_eq!(
+            labels(&svg),
+            vec!["8", "a", "7", "b", "6", "c", "5", "d", "4", "e", "3", "f", "2", "g", "1", "h"]
+        );
+    }
+
+    #[test]
+    fn flipped() {
+        let mut renderer = SvgRenderer::new().unwrap();
+        renderer.set_orientation(Orientation::Black);
+        let svg = renderer.render(&default_board(), 400);
+        let king = format!(
+            r#"<image x="150" y="0" width="50" height="50" xlink:href="{}"/>"#,
+            renderer.pieces[&KING]
+        );
+        assert!(svg.contains(&king));
+        assert_eq!(
+            labels(&svg),
+            vec!["1", "h", "2", "g", "3", "f", "4", "e", "5", "d", "6", "c", "7", "b", "8", "a"]
+        );
+    }
+
+    #[test]
+    fn without_coordinates() {
+        let mut renderer = SvgRenderer::new().unwrap();
+        renderer.set_coordinates(false);
+        let svg = renderer.render(&default_board(), 400);
+        assert!(labels(&svg).is_empty());
+    }
+
+    #[test]
+    fn annotations() {
+        let mut renderer = SvgRenderer::new().unwrap();
+        let e4 = Square::from_name("e4").unwrap();
+        let circle = Annotation::Circle {
+            square: e4,
+            brush: Brush::Red,
+        };
+        renderer.set_annotations(&[circle, circle]);
+        let svg = renderer.render(&default_board(), 400);
+        assert_eq!(svg.matches("<g ").count(), 1);
+        assert!(svg.contains(r#"<circle cx="225" cy="225""#));
+
+        assert!(renderer.remove_annotation(&circle));
+        let svg = renderer.render(&default_board(), 400);
+        assert!(!svg.contains("<g "));
+    }
+}
@@ -0,0 +1,367 @@
+use std::fmt::Write;
+
+use crate::{
+    annotation::Annotation,
+    atlas::linear_to_srgb,
+    board::{Board, Orientation, Square},
+    piece::{Color, Piece, Type},
+    renderer::Renderer,
+    theme::{Highlight, Highlights, Theme},
+};
+
+/// how pieces are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Glyphs {
+    /// the chess symbols, e.g. ♔ for the white king
+    Unicode,
+    /// fen letters, uppercase for white
+    Ascii,
+}
+
+/// how squares are colored, if at all
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Colors {
+    /// plain text, for logs and test failures
+    None,
+    /// the closest of the 256 color palette most terminals support
+    Ansi256,
+    /// the theme's exact colors, for terminals with 24 bit color
+    TrueColor,
+}
+
+/// writes the board as text, for logs, test failures and terminals without a window
+pub struct TextRenderer {
+    theme: Theme,
+    highlights: Highlights,
+    orientation: Orientation,
+    glyphs: Glyphs,
+    colors: Colors,
+    coordinates: bool,
+    annotations: Vec<Annotation>,
+}
+
+impl Default for TextRenderer {
+    fn default() -> TextRenderer {
+        TextRenderer {
+            theme: Theme::default(),
+            highlights: Highlights::default(),
+            orientation: Orientation::White,
+            glyphs: Glyphs::Unicode,
+            colors: Colors::None,
+            coordinates: true,
+            annotations: Vec::new(),
+        }
+    }
+}
+
+impl TextRenderer {
+    pub fn set_glyphs(&mut self, glyphs: Glyphs) {
+        self.glyphs = glyphs;
+    }
+
+    pub fn set_colors(&mut self, colors: Colors) {
+        self.colors = colors;
+    }
+
+    /// writes `board` as eight lines of squares, plus one of files with coordinates and
+    /// one listing the annotations as pgn `%cal`/`%csl` commands if there are any
+    pub fn render(&self, board: &Board) -> String {
+        let mut text = String::new();
+        for row in 0..8 {
+            if self.coordinates {
+                let rank = Square::new(0, 7 - row).oriented(self.orientation).rank;
+                write!(text, "{} ", rank + 1).unwrap();
+            }
+            for column in 0..8 {
+                let square = Square::new(column, 7 - row).oriented(self.orientation);
+                self.write_square(&mut text, square, square.piece(board));
+            }
+            match self.colors {
+                Colors::None => {
+                    text.pop();
+                }
+                _ => text.push_str("\x1b[0m"),
+            }
+            text.push('\n');
+        }
+
+        if self.coordinates {
+            text.push_str("  ");
+            for column in 0..8 {
+                let file = Square::new(column, 0).oriented(self.orientation).file;
+                let letter = (b'a' + file as u8) as char;
+                match self.colors {
+                    Colors::None => write!(text, "{} ", letter).unwrap(),
+                    _ => write!(text, " {} ", letter).unwrap(),
+                }
+            }
+            text.truncate(text.trim_end().len());
+            text.push('\n');
+        }
+
+        if !self.annotations.is_empty() {
+            text.push_str(&self.annotation_commands());
+            text.push('\n');
+        }
+        text
+    }
+
+    fn annotation_commands(&self) -> String {
+        let mut arrows = Vec::new();
+        let mut circles = Vec::new();
+        for annotation in &self.annotations {
+            match *annotation {
+                Annotation::Arrow { from, to, brush } => {
+                    arrows.push(format!("{}{}{}", brush.letter(), from, to))
+                }
+                Annotation::Circle { square, brush } => {
+                    circles.push(format!("{}{}", brush.letter(), square))
+                }
+            }
+        }
+        let mut commands = Vec::new();
+        if !arrows.is_empty() {
+            commands.push(format!("[%cal {}]", arrows.join(",")));
+        }
+        if !circles.is_empty() {
+            commands.push(format!("[%csl {}]", circles.join(",")));
+        }
+        commands.join(" ")
+    }
+
+    fn write_square(&self, text: &mut String, square: Square, piece: Option<Piece>) {
+        if self.colors == Colors::None {
+            let glyph = match piece {
+                Some(piece) => self.glyph(piece, false),
+                None if self.glyphs == Glyphs::Unicode => '·',
+                None => '.',
+            };
+            write!(text, "{} ", glyph).unwrap();
+            return;
+        }
+
+        let background = srgb(self.theme.highlighted_color(square, &self.highlights));
+        text.push_str(&self.escape(48, background));
+        match piece {
+            Some(piece) => {
+                // solid glyphs read better on a colored square, the color tells them apart
+                let foreground = match piece.color {
+                    Color::White => [255, 255, 255],
+                    Color::Black => [0, 0, 0],
+                };
+                text.push_str(&self.escape(38, foreground));
+                write!(text, " {} ", self.glyph(piece, true)).unwrap();
+            }
+            None => text.push_str("   "),
+        }
+    }
+
+    /// the sequence setting the foreground (38) or background (48) color
+    fn escape(&self, target: u8, [r, g, b]: [u8; 3]) -> String {
+        match self.colors {
+            Colors::TrueColor => format!("\x1b[{};2;{};{};{}m", target, r, g, b),
+            _ => format!("\x1b[{};5;{}m", target, ansi256([r, g, b])),
+        }
+    }
+
+    /// `solid` draws both colors with the black symbols, which are filled in
+    fn glyph(&self, piece: Piece, solid: bool) -> char {
+        let white = piece.color == Color::White && !solid;
+        match (self.glyphs, piece.type_) {
+            (Glyphs::Ascii, type_) => {
+                let letter = match type_ {
+                    Type::King => 'K',
+                    Type::Queen => 'Q',
+                    Type::Rook => 'R',
+                    Type::Bishop => 'B',
+                    Type::Knight => 'N',
+                    Type::Pawn => 'P',
+                };
+                match piece.color {
+                    Color::White => letter,
+                    Color::Black => letter.to_ascii_lowercase(),
+                }
+            }
+            (Glyphs::Unicode, Type::King) if white => '♔',
+            (Glyphs::Unicode, Type::Queen) if white => '♕',
+            (Glyphs::Unicode, Type::Rook) if white => '♖',
+            (Glyphs::Unicode, Type::Bishop) if white => '♗',
+            (Glyphs::Unicode, Type::Knight) if white => '♘',
+            (Glyphs::Unicode, Type::Pawn) if white => '♙',
+            (Glyphs::Unicode, Type::King) => '♚',
+            (Glyphs::Unicode, Type::Queen) => '♛',
+            (Glyphs::Unicode, Type::Rook) => '♜',
+            (Glyphs::Unicode, Type::Bishop) => '♝',
+            (Glyphs::Unicode, Type::Knight) => '♞',
+            (Glyphs::Unicode, Type::Pawn) => '♟',
+        }
+    }
+}
+
+impl Renderer for TextRenderer {
+    fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    fn set_highlight(&mut self, kind: Highlight, squares: &[Square]) {
+        self.highlights.set(kind, squares);
+    }
+
+    fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
+    fn set_orientation(&mut self, orientation: Orientation) {
+        self.orientation = orientation;
+    }
+
+    /// whether the ranks are written to the left of the board and the files under it
+    fn set_coordinates(&mut self, coordinates: bool) {
+        self.coordinates = coordinates;
+    }
+
+    /// arrows and circles can't be drawn in text, so they're listed under the board instead
+    fn set_annotations(&mut self, annotations: &[Annotation]) {
+        self.annotations.clear();
+        for &annotation in annotations {
+            if !self.annotations.contains(&annotation) {
+                self.annotations.push(annotation);
+            }
+        }
+    }
+}
+
+fn srgb([r, g, b]: [f32; 3]) -> [u8; 3] {
+    [linear_to_srgb(r), linear_to_srgb(g), linear_to_srgb(b)]
+}
+
+/// the closest color of the 6x6x6 cube in the 256 color palette
+fn ansi256(color: [u8; 3]) -> u8 {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let index = |value: u8| {
+        (0..LEVELS.len())
+            .min_by_key(|&i| (LEVELS[i] as i16 - value as i16).abs())
+            .unwrap() as u8
+    };
+    16 + 36 * index(color[0]) + 6 * index(color[1]) + index(color[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        annotation::{parse_comment, Brush},
+        board::default_board,
+        theme::Highlights,
+    };
+
+    const START: &str = "\
+8 ♜ ♞ ♝ ♛ ♚ ♝ ♞ ♜
+7 ♟ ♟ ♟ ♟ ♟ ♟ ♟ ♟
+6 · · · · · · · ·
+5 · · · · · · · ·
+4 · · · · · · · ·
+3 · · · · · · · ·
+2 ♙ ♙ ♙ ♙ ♙ ♙ ♙ ♙
+1 ♖ ♘ ♗ ♕ ♔ ♗ ♘ ♖
+  a b c d e f g h
+";
+
+    #[test]
+    fn start_position() {
+        assert_eq!(TextRenderer::default().render(&default_board()), START);
+    }
+
+    #[test]
+    fn flipped() {
+        let mut renderer = TextRenderer::default();
+        renderer.set_orientation(Orientation::Black);
+        renderer.set_glyphs(Glyphs::Ascii);
+        assert_eq!(
+            renderer.render(&default_board()),
+            "\
+1 R N B K Q B N R
+2 P P P P P P P P
+3 . . . . . . . .
+4 . . . . . . . .
+5 . . . . . . . .
+6 . . . . . . . .
+7 p p p p p p p p
+8 r n b k q b n r
+  h g f e d c b a
+"
+        );
+    }
+
+    #[test]
+    fn without_coordinates() {
+        let mut renderer = TextRenderer::default();
+        renderer.set_coordinates(false);
+        let text = renderer.render(&default_board());
+        assert_eq!(text.lines().count(), 8);
+        assert_eq!(text.lines().next(), Some("♜ ♞ ♝ ♛ ♚ ♝ ♞ ♜"));
+    }
+
+    #[test]
+    fn annotations_are_listed() {
+        let mut renderer = TextRenderer::default();
+        let e4 = Square::from_name("e4").unwrap();
+        renderer.set_annotations(&[
+            Annotation::Circle {
+                square: e4,
+                brush: Brush::Red,
+            },
+            Annotation::Arrow {
+                from: Square::from_name("e2").unwrap(),
+                to: e4,
+                brush: Brush::Green,
+            },
+        ]);
+        let text = renderer.render(&default_board());
+        assert_eq!(text.lines().last(), Some("[%cal Ge2e4] [%csl Re4]"));
+        assert_eq!(parse_comment(text.lines().last().unwrap()).len(), 2);
+    }
+
+    #[test]
+    fn true_color() {
+        let mut renderer = TextRenderer::default();
+        renderer.set_colors(Colors::TrueColor);
+        let text = renderer.render(&default_board());
+        let theme = Theme::default();
+        let highlights = Highlights::default();
+        let [r, g, b] = srgb(theme.highlighted_color(Square::new(0, 7), &highlights));
+        let first = text.lines().next().unwrap();
+        // a8 has a black rook in black on the light square's exact color
+        assert!(first.starts_with(&format!(
+            "8 \x1b[48;2;{};{};{}m\x1b[38;2;0;0;0m ♜ ",
+            r, g, b
+        )));
+        assert!(first.ends_with("\x1b[0m"));
+        assert_eq!(text.lines().last(), Some("   a  b  c  d  e  f  g  h"));
+        assert!(!text.contains("\x1b[48;5;"));
+    }
+
+    #[test]
+    fn ansi_256_colors() {
+        let mut renderer = TextRenderer::default();
+        renderer.set_colors(Colors::Ansi256);
+        let text = renderer.render(&default_board());
+        let light =
+            srgb(Theme::default().highlighted_color(Square::new(0, 7), &Highlights::default()));
+        let first = text.lines().next().unwrap();
+        assert!(first.starts_with(&format!("8 \x1b[48;5;{}m\x1b[38;5;16m ♜ ", ansi256(light))));
+        // empty squares are only colored
+        let third = text.lines().nth(2).unwrap();
+        assert!(!third.contains("\x1b[38;"));
+        assert!(!text.contains("\x1b[48;2;"));
+
+        assert_eq!(ansi256([0, 0, 0]), 16);
+        assert_eq!(ansi256([255, 255, 255]), 231);
+        assert_eq!(ansi256([255, 0, 0]), 196);
+        assert_eq!(ansi256([100, 140, 170]), 16 + 36 + 6 * 2 + 3);
+    }
+}
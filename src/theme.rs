@@ -0,0 +1,136 @@
+use crate::board::Square;
+
+/// colors of the board, in linear rgba. highlights are blended over the square color using
+/// their alpha
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub light: [f32; 4],
+    pub dark: [f32; 4],
+    /// the origin and destination of the last move
+    pub last_move: [f32; 4],
+    /// the square of the selected piece
+    pub selected: [f32; 4],
+    /// the king when it's in check
+    pub check: [f32; 4],
+}
+
+impl Theme {
+    pub const BROWN: Theme = Theme {
+        light: [0.94, 0.85, 0.71, 1.0],
+        dark: [0.6, 0.4, 0.2, 1.0],
+        last_move: [0.61, 0.78, 0.0, 0.41],
+        selected: [0.08, 0.33, 0.12, 0.5],
+        check: [0.9, 0.0, 0.0, 0.6],
+    };
+
+    pub const GREEN: Theme = Theme {
+        light: [0.87, 0.89, 0.75, 1.0],
+        dark: [0.2, 0.37, 0.16, 1.0],
+        last_move: [0.95, 0.85, 0.1, 0.45],
+        selected: [0.1, 0.45, 0.65, 0.5],
+        check: [0.9, 0.0, 0.0, 0.6],
+    };
+
+    pub const BLUE: Theme = Theme {
+        light: [0.78, 0.84, 0.9, 1.0],
+        dark: [0.27, 0.42, 0.6, 1.0],
+        last_move: [0.55, 0.78, 0.2, 0.45],
+        selected: [0.95, 0.8, 0.2, 0.5],
+        check: [0.9, 0.0, 0.0, 0.6],
+    };
+
+    pub const HIGH_CONTRAST: Theme = Theme {
+        light: [1.0, 1.0, 1.0, 1.0],
+        dark: [0.1, 0.1, 0.1, 1.0],
+        last_move: [1.0, 0.85, 0.0, 0.6],
+        selected: [0.0, 0.6, 1.0, 0.7],
+        check: [1.0, 0.0, 0.0, 0.8],
+    };
+
+    /// the built in themes along with their names
+    pub const PRESETS: [(&'static str, Theme); 4] = [
+        ("brown", Theme::BROWN),
+        ("green", Theme::GREEN),
+        ("blue", Theme::BLUE),
+        ("high-contrast", Theme::HIGH_CONTRAST),
+    ];
+
+    pub fn by_name(name: &str) -> Option<Theme> {
+        Theme::PRESETS
+            .iter()
+            .find(|(preset, _)| preset.eq_ignore_ascii_case(name))
+            .map(|&(_, theme)| theme)
+    }
+
+    /// the color of `square` without any highlights
+    pub fn square_color(&self, square: Square) -> [f32; 4] {
+        if (square.file + square.rank) % 2 == 1 {
+            self.light
+        } else {
+            self.dark
+        }
+    }
+
+    /// the color of `square` with its highlights blended over it, like the board shader does
+    pub fn highlighted_color(&self, square: Square, highlights: &Highlights) -> [f32; 3] {
+        let [r, g, b, _] = self.square_color(square);
+        let mut color = [r, g, b];
+        let kinds = [
+            (Highlight::LastMove, self.last_move),
+            (Highlight::Selected, self.selected),
+            (Highlight::Check, self.check),
+        ];
+        for &(kind, highlight) in &kinds {
+            if highlights.contains(kind, square) {
+                for (channel, &value) in color.iter_mut().zip(&highlight) {
+                    *channel += (value - *channel) * highlight[3];
+                }
+            }
+        }
+        color
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme::BROWN
+    }
+}
+
+/// kinds of square highlight, each drawn with its color from the `Theme`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Highlight {
+    LastMove,
+    Selected,
+    Check,
+}
+
+/// the highlighted squares of each kind as bitmasks indexed by `rank * 8 + file`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Highlights {
+    pub last_move: u64,
+    pub selected: u64,
+    pub check: u64,
+}
+
+impl Highlights {
+    pub fn set(&mut self, kind: Highlight, squares: &[Square]) {
+        let mask = squares.iter().fold(0, |mask, square| {
+            mask | (1 << (square.rank * 8 + square.file))
+        });
+        match kind {
+            Highlight::LastMove => self.last_move = mask,
+            Highlight::Selected => self.selected = mask,
+            Highlight::Check => self.check = mask,
+        }
+    }
+
+    pub fn contains(&self, kind: Highlight, square: Square) -> bool {
+        let mask = match kind {
+            Highlight::LastMove => self.last_move,
+            Highlight::Selected => self.selected,
+            Highlight::Check => self.check,
+        };
+        mask & (1 << (square.rank * 8 + square.file)) != 0
+    }
+}
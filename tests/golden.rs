@@ -0,0 +1,254 @@
+//! renders boards with the software renderer and compares them against the reference images
+//! in `tests/golden`. run with `UPDATE_GOLDEN=1` to write the references again after an
+//! intentional change, and look at the diffs written to `target/golden` when a test fails.
+//! `cargo test -- --ignored` also holds the gpu renderer to the same references, which needs
+//! a graphics adapter.
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use chessboard_rs::{
+    board::{Board, Orientation, Square},
+    rules::{Move, Position},
+    software::SoftwareRenderer,
+    theme::{Highlight, Theme},
+    BoardRenderer, Renderer, DEFAULT_SAMPLE_COUNT,
+};
+use image::{Rgba, RgbaImage};
+
+const SIZE: u32 = 256;
+
+/// how far apart a channel of the rendered and reference pixels may be
+const TOLERANCE: u8 = 2;
+
+fn square(name: &str) -> Square {
+    Square::from_name(name).unwrap()
+}
+
+/// plays `moves` from the start position, given as pairs of squares like `e2e4`
+fn play(moves: &[&str]) -> Position {
+    let mut position = Position::default();
+    for mv in moves {
+        let mv = Move::new(square(&mv[..2]), square(&mv[2..]));
+        assert!(position.play(mv), "{} isn't legal", mv.from);
+    }
+    position
+}
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+fn diff_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("target/golden")
+}
+
+/// sets up a renderer for one of the reference images and returns the board to draw. the
+/// references don't show coordinates, so they're hidden before the setup runs
+type Setup = Box<dyn Fn(&mut dyn Renderer) -> Board>;
+
+/// draws the board `setup` returns with the software renderer and compares it with
+/// `tests/golden/{name}.png`, or writes it there with `UPDATE_GOLDEN`
+fn assert_software(name: &str, setup: impl Fn(&mut dyn Renderer) -> Board) {
+    let mut renderer = SoftwareRenderer::new().unwrap();
+    renderer.set_coordinates(false);
+    let board = setup(&mut renderer);
+    let actual = renderer.render(&board, SIZE);
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        actual
+            .save(golden_dir().join(format!("{}.png", name)))
+            .unwrap();
+        return;
+    }
+    assert_golden(name, name, &actual);
+}
+
+/// compares `actual` with `tests/golden/{name}.png`, writing it and the diff to
+/// `target/golden/{output}.png` and `{output}-diff.png` if they differ
+fn assert_golden(name: &str, output: &str, actual: &RgbaImage) {
+    let path = golden_dir().join(format!("{}.png", name));
+
+    let expected = image::open(&path)
+        .unwrap_or_else(|err| panic!("unable to open {}: {}", path.display(), err))
+        .into_rgba8();
+    assert_eq!(
+        expected.dimensions(),
+        actual.dimensions(),
+        "{} changed size",
+        name
+    );
+
+    // differing pixels are red in the diff, the rest are a faded copy of the reference
+    let mut mismatches = 0;
+    let diff = RgbaImage::from_fn(SIZE, SIZE, |x, y| {
+        let (Rgba(expected), Rgba(actual)) = (expected.get_pixel(x, y), actual.get_pixel(x, y));
+        let matches = expected
+            .iter()
+            .zip(actual)
+            .all(|(&a, &b)| (a as i16 - b as i16).abs() <= TOLERANCE as i16);
+        if matches {
+            let [r, g, b, _] = *expected;
+            Rgba([r / 4 + 191, g / 4 + 191, b / 4 + 191, 255])
+        } else {
+            mismatches += 1;
+            Rgba([255, 0, 0, 255])
+        }
+    });
+    if mismatches > 0 {
+        std::fs::create_dir_all(diff_dir()).unwrap();
+        let actual_path = diff_dir().join(format!("{}.png", output));
+        let diff_path = diff_dir().join(format!("{}-diff.png", output));
+        actual.save(&actual_path).unwrap();
+        diff.save(&diff_path).unwrap();
+        panic!(
+            "{} pixels of {} differ from the reference, see {} and {}",
+            mismatches,
+            output,
+            actual_path.display(),
+            diff_path.display()
+        );
+    }
+}
+
+fn setup_start(_: &mut dyn Renderer) -> Board {
+    Position::default().board
+}
+
+fn setup_flipped(renderer: &mut dyn Renderer) -> Board {
+    renderer.set_orientation(Orientation::Black);
+    play(&["e2e4", "c7c5"]).board
+}
+
+fn setup_highlights(renderer: &mut dyn Renderer) -> Board {
+    renderer.set_highlight(Highlight::LastMove, &[square("h5"), square("f7")]);
+    renderer.set_highlight(Highlight::Selected, &[square("c4")]);
+    renderer.set_highlight(Highlight::Check, &[square("e8")]);
+    play(&["e2e4", "e7e5", "d1h5", "b8c6", "f1c4", "g8f6", "h5f7"]).board
+}
+
+fn setup_flipped_highlights(renderer: &mut dyn Renderer) -> Board {
+    renderer.set_orientation(Orientation::Black);
+    renderer.set_highlight(Highlight::LastMove, &[square("g1"), square("f3")]);
+    play(&["e2e4", "e7e5", "g1f3"]).board
+}
+
+fn setup_theme(theme: Theme) -> impl Fn(&mut dyn Renderer) -> Board {
+    move |renderer| {
+        renderer.set_theme(theme);
+        renderer.set_highlight(Highlight::LastMove, &[square("c2"), square("c4")]);
+        play(&["d2d4", "d7d5", "c2c4"]).board
+    }
+}
+
+fn setup_custom_theme(renderer: &mut dyn Renderer) -> Board {
+    renderer.set_theme(Theme {
+        light: [0.8, 0.6, 0.9, 1.0],
+        dark: [0.3, 0.1, 0.4, 1.0],
+        last_move: [0.0, 1.0, 1.0, 0.5],
+        selected: [1.0, 1.0, 0.0, 0.5],
+        check: [1.0, 0.0, 0.0, 1.0],
+    });
+    renderer.set_highlight(Highlight::Selected, &[square("g1")]);
+    Position::default().board
+}
+
+/// every reference image along with how it's set up
+fn cases() -> Vec<(String, Setup)> {
+    let mut cases: Vec<(String, Setup)> = vec![
+        ("start".into(), Box::new(setup_start)),
+        ("flipped".into(), Box::new(setup_flipped)),
+        ("highlights".into(), Box::new(setup_highlights)),
+        (
+            "flipped-highlights".into(),
+            Box::new(setup_flipped_highlights),
+        ),
+        ("custom-theme".into(), Box::new(setup_custom_theme)),
+    ];
+    for &(name, theme) in Theme::PRESETS.iter() {
+        cases.push((format!("theme-{}", name), Box::new(setup_theme(theme))));
+    }
+    cases
+}
+
+#[test]
+fn start_position() {
+    assert_software("start", setup_start);
+}
+
+#[test]
+fn flipped() {
+    assert_software("flipped", setup_flipped);
+}
+
+#[test]
+fn highlights() {
+    assert_software("highlights", setup_highlights);
+}
+
+#[test]
+fn flipped_highlights() {
+    assert_software("flipped-highlights", setup_flipped_highlights);
+}
+
+#[test]
+fn preset_themes() {
+    for &(name, theme) in Theme::PRESETS.iter() {
+        assert_software(&format!("theme-{}", name), setup_theme(theme));
+    }
+}
+
+#[test]
+fn custom_theme() {
+    assert_software("custom-theme", setup_custom_theme);
+}
+
+/// opens a device without a window, on whichever adapter wgpu prefers
+async fn request_device() -> (wgpu::Device, wgpu::Queue) {
+    let instance = wgpu::Instance::new(wgpu::BackendBit::all());
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            compatible_surface: None,
+        })
+        .await
+        .expect("no graphics adapter");
+    adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                features: wgpu::Features::empty(),
+                limits: wgpu::Limits::default(),
+                label: None,
+            },
+            None,
+        )
+        .await
+        .unwrap()
+}
+
+/// the gpu renderer should draw the same images as the software one, diffs are written as
+/// `target/golden/{name}-gpu.png`
+#[test]
+#[ignore]
+fn gpu_matches_references() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let (device, queue) = runtime.block_on(request_device());
+    let (device, queue) = (Arc::new(device), Arc::new(queue));
+    for (name, setup) in cases() {
+        let mut renderer = BoardRenderer::new(
+            device.clone(),
+            queue.clone(),
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            DEFAULT_SAMPLE_COUNT,
+        )
+        .unwrap();
+        renderer.set_coordinates(false);
+        let board = setup(&mut renderer);
+        let actual = renderer.render_to_image(&board, SIZE).unwrap();
+        assert_golden(&name, &format!("{}-gpu", name), &actual);
+    }
+}